@@ -0,0 +1,77 @@
+//! Compares decoding a large `GUILD_CREATE` with the op/t-directed
+//! decoder against letting serde guess the payload object's type
+//! with an untagged enum, which is what derust used to do.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use derust::types::gateway::opcodes::OPCode;
+use derust::types::gateway::payloads::{GatewayMessages, HelloObject};
+use derust::types::guild::Guild;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+#[allow(dead_code)]
+enum UntaggedObjects {
+    Hello(HelloObject),
+    InvalidSession(bool),
+    Other(serde_json::Value),
+}
+
+#[derive(Deserialize)]
+struct UntaggedPayload {
+    op: OPCode,
+    d: Option<UntaggedObjects>,
+    s: Option<u64>,
+    t: Option<String>,
+}
+
+fn decode_untagged(text: &str) -> Guild {
+    let payload: UntaggedPayload = serde_json::from_str(text).unwrap();
+    assert_eq!(payload.op, OPCode::Dispatch);
+    assert!(payload.s.is_some() && payload.t.is_some());
+    match payload.d {
+        Some(UntaggedObjects::Other(d)) => serde_json::from_value(d).unwrap(),
+        _ => unreachable!(),
+    }
+}
+
+/// A `GUILD_CREATE` for a guild with `members` members.
+fn guild_create(member_count: usize) -> String {
+    let roles = (0..100)
+        .map(|i| format!(
+            r#"{{"id":"{}","name":"role {}","color":0,"hoist":false,"position":{},"permissions":104324673,"managed":false,"mentionable":false}}"#,
+            1000 + i, i, i
+        ))
+        .collect::<Vec<_>>()
+        .join(",");
+    let channels = (0..200)
+        .map(|i| format!(
+            r#"{{"id":"{}","type":0,"guild_id":"1","position":{},"permission_overwrites":[{{"id":"1000","type":"role","allow":0,"deny":2048}}],"name":"channel-{}","topic":"some topic","nsfw":false,"last_message_id":null,"rate_limit_per_user":0,"parent_id":null}}"#,
+            5000 + i, i, i
+        ))
+        .collect::<Vec<_>>()
+        .join(",");
+    let members = (0..member_count)
+        .map(|i| format!(
+            r#"{{"user":{{"id":"{}","username":"user {}","discriminator":"0001","avatar":null,"public_flags":0}},"nick":null,"roles":["1001","1002"],"joined_at":"2020-01-01T00:00:00.000000+00:00","premium_since":null,"deaf":false,"mute":false}}"#,
+            100_000 + i, i
+        ))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!(
+        r#"{{"t":"GUILD_CREATE","s":2,"op":0,"d":{{"id":"1","name":"big guild","icon":null,"splash":null,"discovery_splash":null,"owner_id":"100000","region":"europe","afk_channel_id":null,"afk_timeout":300,"verification_level":1,"default_message_notifications":0,"explicit_content_filter":0,"roles":[{}],"emojis":[],"features":[],"mfa_level":0,"application_id":null,"system_channel_id":null,"system_channel_flags":0,"rules_channel_id":null,"joined_at":"2020-01-01T00:00:00.000000+00:00","large":true,"member_count":{},"voice_states":[],"members":[{}],"channels":[{}],"presences":[],"vanity_url_code":null,"description":null,"banner":null,"premium_tier":0,"preferred_locale":"en-US","public_updates_channel_id":null}}}}"#,
+        roles, member_count, members, channels
+    )
+}
+
+fn bench_guild_create(c: &mut Criterion) {
+    let text = guild_create(5000);
+    let mut group = c.benchmark_group("GUILD_CREATE (5000 members)");
+    group.bench_function("untagged", |b| b.iter(|| decode_untagged(black_box(&text))));
+    group.bench_function("op/t-directed", |b| {
+        b.iter(|| GatewayMessages::decode(black_box(&text)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_guild_create);
+criterion_main!(benches);
@@ -0,0 +1,295 @@
+use reqwest::Method;
+use enumflags2::BitFlags;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::types::Snowflake;
+use crate::types::channel::{Channel, ChannelType};
+use crate::types::error::DerustError;
+use crate::types::message::{AllowedMentions, Embed, Message, MessageReference};
+use crate::types::permission::{PermissionOverwrite, Permissions};
+use super::{Http, Request, file::AttachmentFile, routing::Route, validate_limit};
+
+/// How long a message can be, in characters.
+const MAX_CONTENT_LENGTH: usize = 2000;
+
+/// How old messages can be to be bulk deleted, in milliseconds.
+const BULK_DELETE_MAX_AGE: u64 = 14 * 24 * 60 * 60 * 1000;
+
+/// The fields of a channel to modify, the ones left to `None`
+/// being kept as they are, and the ones set to `Some(None)`
+/// being cleared.
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ModifyChannel {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Only between text and news channels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<ChannelType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nsfw: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit_per_user: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_limit: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permission_overwrites: Option<Vec<PermissionOverwrite>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<Option<Snowflake>>,
+}
+
+/// A message to send, which needs at least
+/// either a `content`, an `embed` or `files`.
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CreateMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Sent back in the [`MessageCreate`] event,
+    /// to tell that the message was sent.
+    ///
+    /// [`MessageCreate`]: crate::types::gateway::payloads::DiscordEvent::MessageCreate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    pub tts: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embed: Option<Embed>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_mentions: Option<AllowedMentions>,
+    /// The message this one is a reply to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_reference: Option<MessageReference>,
+    /// Uploaded as attachments of the message.
+    #[serde(skip)]
+    pub files: Vec<AttachmentFile>,
+}
+
+impl CreateMessage {
+    /// A message with only text in it.
+    pub fn new(content: String) -> Self {
+        CreateMessage {
+            content: Some(content),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), DerustError> {
+        if self.content.is_none() && self.embed.is_none() && self.files.is_empty() {
+            return Err(DerustError::InvalidInput(
+                String::from("a message needs either a content, an embed or files")
+            ));
+        }
+        validate_content(&self.content)
+    }
+
+    /// The permissions needed to send this message.
+    fn required_permissions(&self) -> BitFlags<Permissions> {
+        let mut required = Permissions::ViewChannel | Permissions::SendMessages;
+        if self.tts {
+            required |= Permissions::SendTTSMessages;
+        }
+        if self.embed.is_some() {
+            required |= Permissions::EmbedLinks;
+        }
+        if !self.files.is_empty() {
+            required |= Permissions::AttachFiles;
+        }
+        required
+    }
+}
+
+/// The fields of a message to edit, the ones left
+/// to `None` being kept as they are.
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct EditMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embed: Option<Embed>,
+    /// Only `SUPPRESS_EMBEDS` can be set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_mentions: Option<AllowedMentions>,
+}
+
+/// Which messages of a channel to get, relative to
+/// another message, with [`Http::get_messages`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MessagesPosition {
+    Before(Snowflake),
+    After(Snowflake),
+    Around(Snowflake),
+}
+
+#[derive(Serialize)]
+struct BulkDeleteBody<'a> {
+    messages: &'a [Snowflake],
+}
+
+pub(crate) fn validate_content(content: &Option<String>) -> Result<(), DerustError> {
+    match content {
+        Some(c) if c.chars().count() > MAX_CONTENT_LENGTH => Err(DerustError::InvalidInput(
+            format!("message content must be at most {} characters long", MAX_CONTENT_LENGTH)
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl Http {
+    pub async fn get_channel(&self, channel_id: Snowflake) -> Result<Channel, DerustError> {
+        let route = Route::new(Method::GET, format!("/channels/{}", channel_id));
+        self.request(Request::new(route)).await
+    }
+
+    pub async fn modify_channel(
+        &self,
+        channel_id: Snowflake,
+        channel: &ModifyChannel,
+        reason: Option<&str>,
+    ) -> Result<Channel, DerustError> {
+        self.require_channel_permissions(channel_id, Permissions::ManageChannels.into())?;
+        let route = Route::new(Method::PATCH, format!("/channels/{}", channel_id));
+        self.request(Request::new(route).reason(reason)?.json(channel)?).await
+    }
+
+    /// Delete a guild channel, or close a private one,
+    /// returning the channel that was deleted.
+    pub async fn delete_channel(
+        &self,
+        channel_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<Channel, DerustError> {
+        self.require_channel_permissions(channel_id, Permissions::ManageChannels.into())?;
+        let route = Route::new(Method::DELETE, format!("/channels/{}", channel_id));
+        self.request(Request::new(route).reason(reason)?).await
+    }
+
+    /// Get up to `limit` messages of a channel, between 1 and 100
+    /// (default 50), the latest ones if `position` is `None`.
+    pub async fn get_messages(
+        &self,
+        channel_id: Snowflake,
+        position: Option<MessagesPosition>,
+        limit: Option<u8>,
+    ) -> Result<Vec<Message>, DerustError> {
+        validate_limit(limit, 100)?;
+        let route = Route::new(Method::GET, format!("/channels/{}/messages", channel_id));
+        let mut request = Request::new(route).query("limit", limit);
+        request = match position {
+            Some(MessagesPosition::Before(id)) => request.query("before", Some(id)),
+            Some(MessagesPosition::After(id)) => request.query("after", Some(id)),
+            Some(MessagesPosition::Around(id)) => request.query("around", Some(id)),
+            None => request,
+        };
+        self.request(request).await
+    }
+
+    pub async fn get_message(
+        &self,
+        channel_id: Snowflake,
+        message_id: Snowflake,
+    ) -> Result<Message, DerustError> {
+        let route = Route::new(Method::GET, format!("/channels/{}/messages/{}", channel_id, message_id));
+        self.request(Request::new(route)).await
+    }
+
+    pub async fn create_message(
+        &self,
+        channel_id: Snowflake,
+        message: &CreateMessage,
+    ) -> Result<Message, DerustError> {
+        message.validate()?;
+        self.require_channel_permissions(channel_id, message.required_permissions())?;
+        let route = Route::new(Method::POST, format!("/channels/{}/messages", channel_id));
+        self.request(Request::new(route).json(message)?.files(message.files.clone())?).await
+    }
+
+    /// Edit a message, which has to be sent by
+    /// the bot unless only `flags` are edited.
+    pub async fn edit_message(
+        &self,
+        channel_id: Snowflake,
+        message_id: Snowflake,
+        message: &EditMessage,
+    ) -> Result<Message, DerustError> {
+        validate_content(&message.content)?;
+        let route = Route::new(Method::PATCH, format!("/channels/{}/messages/{}", channel_id, message_id));
+        self.request(Request::new(route).json(message)?).await
+    }
+
+    pub async fn delete_message(
+        &self,
+        channel_id: Snowflake,
+        message_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        let route = Route::new(Method::DELETE, format!("/channels/{}/messages/{}", channel_id, message_id));
+        self.request_empty(Request::new(route).reason(reason)?).await
+    }
+
+    /// Delete between 2 and 100 messages at once, which
+    /// can't be older than 2 weeks.
+    pub async fn bulk_delete_messages(
+        &self,
+        channel_id: Snowflake,
+        message_ids: &[Snowflake],
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        if !(2..=100).contains(&message_ids.len()) {
+            return Err(DerustError::InvalidInput(
+                format!("between 2 and 100 messages can be bulk deleted, got {}", message_ids.len())
+            ));
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        if let Some(id) = message_ids.iter().find(|id| id.timestamp() + BULK_DELETE_MAX_AGE < now) {
+            return Err(DerustError::InvalidInput(
+                format!("message {} is older than 2 weeks and can't be bulk deleted", id)
+            ));
+        }
+        self.require_channel_permissions(channel_id, Permissions::ManageMessages.into())?;
+        let route = Route::new(Method::POST, format!("/channels/{}/messages/bulk-delete", channel_id));
+        let request = Request::new(route)
+            .reason(reason)?
+            .json(&BulkDeleteBody { messages: message_ids })?;
+        self.request_empty(request).await
+    }
+
+    /// Show the bot as typing in a channel for 10 seconds,
+    /// or until it sends a message.
+    pub async fn trigger_typing(&self, channel_id: Snowflake) -> Result<(), DerustError> {
+        let route = Route::new(Method::POST, format!("/channels/{}/typing", channel_id));
+        self.request_empty(Request::new(route)).await
+    }
+
+    pub async fn get_pinned_messages(&self, channel_id: Snowflake) -> Result<Vec<Message>, DerustError> {
+        let route = Route::new(Method::GET, format!("/channels/{}/pins", channel_id));
+        self.request(Request::new(route)).await
+    }
+
+    pub async fn pin_message(
+        &self,
+        channel_id: Snowflake,
+        message_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        self.require_channel_permissions(channel_id, Permissions::ManageMessages.into())?;
+        let route = Route::new(Method::PUT, format!("/channels/{}/pins/{}", channel_id, message_id));
+        self.request_empty(Request::new(route).reason(reason)?).await
+    }
+
+    pub async fn unpin_message(
+        &self,
+        channel_id: Snowflake,
+        message_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        self.require_channel_permissions(channel_id, Permissions::ManageMessages.into())?;
+        let route = Route::new(Method::DELETE, format!("/channels/{}/pins/{}", channel_id, message_id));
+        self.request_empty(Request::new(route).reason(reason)?).await
+    }
+}
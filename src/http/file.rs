@@ -0,0 +1,86 @@
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use crate::types::error::DerustError;
+
+/// How much can be uploaded with a single message, in bytes,
+/// for every file attached to it together.
+pub const MAX_UPLOAD_SIZE: u64 = 8 * 1024 * 1024;
+
+/// A file to upload as an attachment of a message.
+///
+/// Files are read in memory when they are constructed, so that
+/// their size can be checked before uploading them, and the
+/// request can be retried if needed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AttachmentFile {
+    /// The name the file is shown with in Discord.
+    pub filename: String,
+    pub(crate) data: Vec<u8>,
+}
+
+impl AttachmentFile {
+    pub fn from_bytes(filename: String, data: Vec<u8>) -> Self {
+        AttachmentFile { filename, data }
+    }
+
+    /// Read the file at `path`, keeping its name.
+    pub async fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, DerustError> {
+        let path = path.as_ref();
+        let filename = path.file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| DerustError::InvalidInput(format!("{} is not a file", path.display())))?;
+        let io_error = |e: std::io::Error| DerustError::InvalidInput(
+            format!("failed to read {}: {}", path.display(), e)
+        );
+        let size = tokio::fs::metadata(path).await.map_err(io_error)?.len();
+        check_size(&filename, size)?;
+        let data = tokio::fs::read(path).await.map_err(io_error)?;
+        Ok(AttachmentFile { filename, data })
+    }
+
+    /// Read `reader` until its end, giving up as soon as
+    /// it is over the upload limit.
+    pub async fn from_reader<R: AsyncRead + Unpin>(filename: String, reader: R) -> Result<Self, DerustError> {
+        let mut data = Vec::new();
+        reader.take(MAX_UPLOAD_SIZE + 1).read_to_end(&mut data).await
+            .map_err(|e| DerustError::InvalidInput(format!("failed to read {}: {}", filename, e)))?;
+        if data.len() as u64 > MAX_UPLOAD_SIZE {
+            // the rest of it wasn't read, so its size isn't known.
+            return Err(DerustError::InvalidInput(
+                format!("{} is over the upload limit of {}", filename, format_size(MAX_UPLOAD_SIZE))
+            ));
+        }
+        Ok(AttachmentFile { filename, data })
+    }
+
+    /// Size of the file, in bytes.
+    pub fn size(&self) -> u64 {
+        self.data.len() as u64
+    }
+}
+
+fn check_size(filename: &str, size: u64) -> Result<(), DerustError> {
+    if size > MAX_UPLOAD_SIZE {
+        return Err(DerustError::InvalidInput(format!(
+            "{} is {}, over the upload limit of {}",
+            filename, format_size(size), format_size(MAX_UPLOAD_SIZE)
+        )));
+    }
+    Ok(())
+}
+
+/// Check that `files` can be uploaded together.
+pub(crate) fn validate_files(files: &[AttachmentFile]) -> Result<(), DerustError> {
+    let total = files.iter().map(AttachmentFile::size).sum::<u64>();
+    if total > MAX_UPLOAD_SIZE {
+        return Err(DerustError::InvalidInput(format!(
+            "the {} files are {} together, over the upload limit of {} per message",
+            files.len(), format_size(total), format_size(MAX_UPLOAD_SIZE)
+        )));
+    }
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.2} MB", bytes as f64 / (1024.0 * 1024.0))
+}
@@ -0,0 +1,454 @@
+use reqwest::Method;
+use enumflags2::BitFlags;
+use crate::types::Snowflake;
+use crate::types::error::DerustError;
+use crate::types::guild::{
+    AuditLog, AuditLogEvent, Ban, ExplicitFilterLevel, Guild, GuildMember, Integration, VerificationLevel,
+};
+use crate::types::invite::Invite;
+use crate::types::permission::{Permissions, permissions_string};
+use crate::types::role::Role;
+use crate::types::voice::VoiceRegion;
+use super::{Http, Request, routing::Route, validate_limit};
+
+/// The fields of a guild to modify, the ones left to `None`
+/// being kept as they are, and the ones set to `Some(None)`
+/// being cleared.
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ModifyGuild {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// `Some(None)` for the region to be chosen automatically.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification_level: Option<VerificationLevel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_message_notifications: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explicit_content_filter: Option<ExplicitFilterLevel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub afk_channel_id: Option<Option<Snowflake>>,
+    /// In seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub afk_timeout: Option<i32>,
+    /// As a data URI, like `data:image/png;base64,...`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<Option<String>>,
+    /// Only the owner can transfer the guild.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner_id: Option<Snowflake>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub splash: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub banner: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_channel_id: Option<Option<Snowflake>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rules_channel_id: Option<Option<Snowflake>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_updates_channel_id: Option<Option<Snowflake>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_locale: Option<String>,
+}
+
+/// The fields of a guild member to modify, the ones left to
+/// `None` being kept as they are, and the ones set to `Some(None)`
+/// being cleared.
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ModifyMember {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nick: Option<Option<String>>,
+    /// Replaces every role of the member.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<Snowflake>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mute: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deaf: Option<bool>,
+    /// The voice channel to move the member to, if they are
+    /// connected to one, or `Some(None)` to disconnect them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<Option<Snowflake>>,
+}
+
+impl ModifyMember {
+    /// The permissions needed to modify these fields.
+    fn required_permissions(&self) -> BitFlags<Permissions> {
+        let mut required = BitFlags::empty();
+        if self.nick.is_some() {
+            required |= Permissions::ManageNicknames;
+        }
+        if self.roles.is_some() {
+            required |= Permissions::ManageRoles;
+        }
+        if self.mute.is_some() {
+            required |= Permissions::MuteMembers;
+        }
+        if self.deaf.is_some() {
+            required |= Permissions::DeafenMembers;
+        }
+        if self.channel_id.is_some() {
+            required |= Permissions::MoveMembers;
+        }
+        required
+    }
+}
+
+/// The fields of a role to create or modify, the ones
+/// left to `None` being kept as they are, or set to
+/// their default when creating it.
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct EditRole {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "permissions_string::option")]
+    pub permissions: Option<BitFlags<Permissions>>,
+    /// RGB value.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<i32>,
+    /// Whether the members with this role are
+    /// displayed separately in the sidebar.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hoist: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mentionable: Option<bool>,
+}
+
+/// The filters of the entries to get with [`Http::get_audit_log`],
+/// every entry being returned if they are left to `None`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AuditLogFilter {
+    /// Only the entries of actions made by this user.
+    pub user_id: Option<Snowflake>,
+    pub action_type: Option<AuditLogEvent>,
+    /// Only the entries before this one.
+    pub before: Option<Snowflake>,
+    /// Between 1 and 100, default 50.
+    pub limit: Option<u8>,
+}
+
+#[derive(Serialize)]
+struct RolePosition {
+    id: Snowflake,
+    position: i32,
+}
+
+#[derive(Deserialize)]
+struct PruneCount {
+    pruned: Option<u32>,
+}
+
+/// The IDs of `ids` separated by commas, for the query string.
+fn join_ids(ids: &[Snowflake]) -> Option<String> {
+    if ids.is_empty() {
+        return None;
+    }
+    Some(ids.iter().map(Snowflake::to_string).collect::<Vec<_>>().join(","))
+}
+
+fn validate_prune_days(days: Option<u8>) -> Result<(), DerustError> {
+    match days {
+        Some(days) if !(1..=30).contains(&days) => Err(DerustError::InvalidInput(
+            format!("prune days must be between 1 and 30, got {}", days)
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl Http {
+    /// Get a guild, along with its approximate member
+    /// and presence counts if `with_counts` is set.
+    pub async fn get_guild(&self, guild_id: Snowflake, with_counts: bool) -> Result<Guild, DerustError> {
+        let route = Route::new(Method::GET, format!("/guilds/{}", guild_id));
+        self.request(Request::new(route).query("with_counts", Some(with_counts))).await
+    }
+
+    pub async fn modify_guild(
+        &self,
+        guild_id: Snowflake,
+        guild: &ModifyGuild,
+        reason: Option<&str>,
+    ) -> Result<Guild, DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::ManageGuild.into())?;
+        let route = Route::new(Method::PATCH, format!("/guilds/{}", guild_id));
+        self.request(Request::new(route).reason(reason)?.json(guild)?).await
+    }
+
+    /// Get up to `limit` members of a guild, between 1 and 1000
+    /// (default 1), ordered by their ID.
+    ///
+    /// To get the next page, pass the ID of the last member
+    /// of this one as `after`.
+    pub async fn get_members(
+        &self,
+        guild_id: Snowflake,
+        after: Option<Snowflake>,
+        limit: Option<u16>,
+    ) -> Result<Vec<GuildMember>, DerustError> {
+        validate_limit(limit, 1000)?;
+        let route = Route::new(Method::GET, format!("/guilds/{}/members", guild_id));
+        self.request(Request::new(route).query("after", after).query("limit", limit)).await
+    }
+
+    /// Get up to `limit` members whose username or nickname
+    /// starts with `query`, between 1 and 1000 (default 1).
+    pub async fn search_members(
+        &self,
+        guild_id: Snowflake,
+        query: &str,
+        limit: Option<u16>,
+    ) -> Result<Vec<GuildMember>, DerustError> {
+        validate_limit(limit, 1000)?;
+        let route = Route::new(Method::GET, format!("/guilds/{}/members/search", guild_id));
+        self.request(Request::new(route).query("query", Some(query)).query("limit", limit)).await
+    }
+
+    pub async fn get_member(
+        &self,
+        guild_id: Snowflake,
+        user_id: Snowflake,
+    ) -> Result<GuildMember, DerustError> {
+        let route = Route::new(Method::GET, format!("/guilds/{}/members/{}", guild_id, user_id));
+        self.request(Request::new(route)).await
+    }
+
+    pub async fn modify_member(
+        &self,
+        guild_id: Snowflake,
+        user_id: Snowflake,
+        member: &ModifyMember,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        self.require_guild_permissions(guild_id, member.required_permissions())?;
+        if member.nick.is_some() || member.roles.is_some() {
+            self.require_above_member(guild_id, user_id)?;
+        }
+        let route = Route::new(Method::PATCH, format!("/guilds/{}/members/{}", guild_id, user_id));
+        self.request_empty(Request::new(route).reason(reason)?.json(member)?).await
+    }
+
+    pub async fn add_member_role(
+        &self,
+        guild_id: Snowflake,
+        user_id: Snowflake,
+        role_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::ManageRoles.into())?;
+        self.require_above_role(guild_id, role_id)?;
+        let path = format!("/guilds/{}/members/{}/roles/{}", guild_id, user_id, role_id);
+        self.request_empty(Request::new(Route::new(Method::PUT, path)).reason(reason)?).await
+    }
+
+    pub async fn remove_member_role(
+        &self,
+        guild_id: Snowflake,
+        user_id: Snowflake,
+        role_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::ManageRoles.into())?;
+        self.require_above_role(guild_id, role_id)?;
+        let path = format!("/guilds/{}/members/{}/roles/{}", guild_id, user_id, role_id);
+        self.request_empty(Request::new(Route::new(Method::DELETE, path)).reason(reason)?).await
+    }
+
+    /// Remove a member from a guild, who can join it
+    /// again with an invite.
+    pub async fn kick_member(
+        &self,
+        guild_id: Snowflake,
+        user_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::KickMembers.into())?;
+        self.require_above_member(guild_id, user_id)?;
+        let route = Route::new(Method::DELETE, format!("/guilds/{}/members/{}", guild_id, user_id));
+        self.request_empty(Request::new(route).reason(reason)?).await
+    }
+
+    pub async fn get_bans(&self, guild_id: Snowflake) -> Result<Vec<Ban>, DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::BanMembers.into())?;
+        let route = Route::new(Method::GET, format!("/guilds/{}/bans", guild_id));
+        self.request(Request::new(route)).await
+    }
+
+    pub async fn get_ban(&self, guild_id: Snowflake, user_id: Snowflake) -> Result<Ban, DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::BanMembers.into())?;
+        let route = Route::new(Method::GET, format!("/guilds/{}/bans/{}", guild_id, user_id));
+        self.request(Request::new(route)).await
+    }
+
+    /// Ban a user from a guild, deleting the messages they sent
+    /// in the last `delete_message_days`, between 0 and 7.
+    pub async fn create_ban(
+        &self,
+        guild_id: Snowflake,
+        user_id: Snowflake,
+        delete_message_days: Option<u8>,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        if let Some(days) = delete_message_days {
+            if days > 7 {
+                return Err(DerustError::InvalidInput(
+                    format!("delete_message_days must be between 0 and 7, got {}", days)
+                ));
+            }
+        }
+        self.require_guild_permissions(guild_id, Permissions::BanMembers.into())?;
+        self.require_above_member(guild_id, user_id)?;
+        let route = Route::new(Method::PUT, format!("/guilds/{}/bans/{}", guild_id, user_id));
+        self.request_empty(Request::new(route).reason(reason)?.query("delete-message-days", delete_message_days)).await
+    }
+
+    pub async fn remove_ban(
+        &self,
+        guild_id: Snowflake,
+        user_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::BanMembers.into())?;
+        let route = Route::new(Method::DELETE, format!("/guilds/{}/bans/{}", guild_id, user_id));
+        self.request_empty(Request::new(route).reason(reason)?).await
+    }
+
+    pub async fn get_roles(&self, guild_id: Snowflake) -> Result<Vec<Role>, DerustError> {
+        let route = Route::new(Method::GET, format!("/guilds/{}/roles", guild_id));
+        self.request(Request::new(route)).await
+    }
+
+    pub async fn create_role(
+        &self,
+        guild_id: Snowflake,
+        role: &EditRole,
+        reason: Option<&str>,
+    ) -> Result<Role, DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::ManageRoles.into())?;
+        let route = Route::new(Method::POST, format!("/guilds/{}/roles", guild_id));
+        self.request(Request::new(route).reason(reason)?.json(role)?).await
+    }
+
+    /// Move roles to new positions, given as `(role ID, position)`,
+    /// returning every role of the guild.
+    pub async fn modify_role_positions(
+        &self,
+        guild_id: Snowflake,
+        positions: &[(Snowflake, i32)],
+        reason: Option<&str>,
+    ) -> Result<Vec<Role>, DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::ManageRoles.into())?;
+        let body = positions.iter()
+            .map(|&(id, position)| RolePosition { id, position })
+            .collect::<Vec<_>>();
+        let route = Route::new(Method::PATCH, format!("/guilds/{}/roles", guild_id));
+        self.request(Request::new(route).reason(reason)?.json(&body)?).await
+    }
+
+    pub async fn modify_role(
+        &self,
+        guild_id: Snowflake,
+        role_id: Snowflake,
+        role: &EditRole,
+        reason: Option<&str>,
+    ) -> Result<Role, DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::ManageRoles.into())?;
+        self.require_above_role(guild_id, role_id)?;
+        let route = Route::new(Method::PATCH, format!("/guilds/{}/roles/{}", guild_id, role_id));
+        self.request(Request::new(route).reason(reason)?.json(role)?).await
+    }
+
+    pub async fn delete_role(
+        &self,
+        guild_id: Snowflake,
+        role_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::ManageRoles.into())?;
+        self.require_above_role(guild_id, role_id)?;
+        let route = Route::new(Method::DELETE, format!("/guilds/{}/roles/{}", guild_id, role_id));
+        self.request_empty(Request::new(route).reason(reason)?).await
+    }
+
+    /// How many members would be removed by a prune of the members
+    /// inactive for `days`, between 1 and 30 (default 7).
+    ///
+    /// Members with roles are left out, unless they
+    /// only have roles from `include_roles`.
+    pub async fn get_prune_count(
+        &self,
+        guild_id: Snowflake,
+        days: Option<u8>,
+        include_roles: &[Snowflake],
+    ) -> Result<u32, DerustError> {
+        validate_prune_days(days)?;
+        self.require_guild_permissions(guild_id, Permissions::KickMembers.into())?;
+        let route = Route::new(Method::GET, format!("/guilds/{}/prune", guild_id));
+        let request = Request::new(route)
+            .query("days", days)
+            .query("include_roles", join_ids(include_roles));
+        let count: PruneCount = self.request(request).await?;
+        Ok(count.pruned.unwrap_or(0))
+    }
+
+    /// Remove the members inactive for `days`, between 1 and 30
+    /// (default 7), like [`get_prune_count`](#method.get_prune_count).
+    ///
+    /// The number of members removed is only returned if
+    /// `compute_prune_count` is set, which is discouraged
+    /// for large guilds.
+    pub async fn begin_prune(
+        &self,
+        guild_id: Snowflake,
+        days: Option<u8>,
+        compute_prune_count: bool,
+        include_roles: &[Snowflake],
+        reason: Option<&str>,
+    ) -> Result<Option<u32>, DerustError> {
+        validate_prune_days(days)?;
+        self.require_guild_permissions(guild_id, Permissions::KickMembers.into())?;
+        let route = Route::new(Method::POST, format!("/guilds/{}/prune", guild_id));
+        let request = Request::new(route).reason(reason)?
+            .query("days", days)
+            .query("compute_prune_count", Some(compute_prune_count))
+            .query("include_roles", join_ids(include_roles));
+        let count: PruneCount = self.request(request).await?;
+        Ok(count.pruned)
+    }
+
+    pub async fn get_guild_voice_regions(&self, guild_id: Snowflake) -> Result<Vec<VoiceRegion>, DerustError> {
+        let route = Route::new(Method::GET, format!("/guilds/{}/regions", guild_id));
+        self.request(Request::new(route)).await
+    }
+
+    /// Get the invites of a guild, along with their metadata.
+    pub async fn get_guild_invites(&self, guild_id: Snowflake) -> Result<Vec<Invite>, DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::ManageGuild.into())?;
+        let route = Route::new(Method::GET, format!("/guilds/{}/invites", guild_id));
+        self.request(Request::new(route)).await
+    }
+
+    pub async fn get_integrations(&self, guild_id: Snowflake) -> Result<Vec<Integration>, DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::ManageGuild.into())?;
+        let route = Route::new(Method::GET, format!("/guilds/{}/integrations", guild_id));
+        self.request(Request::new(route)).await
+    }
+
+    /// Get the entries of the audit log of a guild matching
+    /// `filter`, from the newest to the oldest.
+    pub async fn get_audit_log(
+        &self,
+        guild_id: Snowflake,
+        filter: &AuditLogFilter,
+    ) -> Result<AuditLog, DerustError> {
+        validate_limit(filter.limit, 100)?;
+        self.require_guild_permissions(guild_id, Permissions::ViewAuditLog.into())?;
+        let route = Route::new(Method::GET, format!("/guilds/{}/audit-logs", guild_id));
+        let request = Request::new(route)
+            .query("user_id", filter.user_id)
+            .query("action_type", filter.action_type.map(|a| a as u8))
+            .query("before", filter.before)
+            .query("limit", filter.limit);
+        self.request(request).await
+    }
+}
@@ -0,0 +1,526 @@
+//! # HTTP
+//! The client every request to the REST API goes through,
+//! which keeps track of the ratelimits of the API so that
+//! requests wait for their turn instead of being rejected.
+//!
+//! The endpoints making changes that show up in the audit log of
+//! a guild take an optional reason, shown along with the change.
+//!
+//! ```rust,no_run
+//! use derust::http::Http;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let http = Http::new("token").expect("Invalid token");
+//!     let user = http.current_user().await.expect("Failed to get the current user");
+//! }
+//! ```
+use std::sync::Arc;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::{Method, StatusCode};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use tokio::time::{delay_for, Duration};
+use tracing::warn;
+use serde::{Serialize, de::DeserializeOwned};
+use crate::types::error::{DerustError, parse_response};
+use crate::types::user::User;
+
+pub mod routing;
+pub mod channel;
+pub mod reaction;
+pub mod guild;
+pub mod file;
+pub mod webhook;
+pub(crate) mod ratelimit;
+#[cfg(feature = "cache")]
+pub(crate) mod preflight;
+
+use routing::Route;
+use ratelimit::Ratelimiter;
+use file::AttachmentFile;
+
+/// A request to the API, built by the functions of the types
+/// which call an endpoint before being given to [`Http`].
+#[derive(Debug)]
+pub struct Request {
+    pub(crate) route: Route,
+    pub(crate) query: Vec<(&'static str, String)>,
+    /// Serialized JSON body.
+    pub(crate) json: Option<Vec<u8>>,
+    /// Percent-encoded reason shown in the audit log.
+    pub(crate) reason: Option<String>,
+    /// Uploaded along with the JSON body, which is then
+    /// sent as `payload_json`.
+    pub(crate) files: Vec<AttachmentFile>,
+}
+
+/// How long an audit log reason can be, in characters.
+const MAX_REASON_LENGTH: usize = 512;
+
+/// Check that the `limit` of items to get, if there is
+/// one, is between 1 and the `max` the endpoint allows.
+pub(crate) fn validate_limit<T: Into<u32>>(limit: Option<T>, max: u32) -> Result<(), DerustError> {
+    if let Some(limit) = limit.map(Into::into) {
+        if !(1..=max).contains(&limit) {
+            return Err(DerustError::InvalidInput(
+                format!("limit must be between 1 and {}, got {}", max, limit)
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl Request {
+    pub fn new(route: Route) -> Self {
+        Request {
+            route,
+            query: Vec::new(),
+            json: None,
+            reason: None,
+            files: Vec::new(),
+        }
+    }
+
+    /// Upload `files` with the request, as `multipart/form-data`.
+    pub fn files(mut self, files: Vec<AttachmentFile>) -> Result<Self, DerustError> {
+        file::validate_files(&files)?;
+        self.files = files;
+        Ok(self)
+    }
+
+    /// Show `reason` in the audit log entry of the action
+    /// made by the request, if there is one.
+    pub fn reason(mut self, reason: Option<&str>) -> Result<Self, DerustError> {
+        if let Some(reason) = reason {
+            if reason.chars().count() > MAX_REASON_LENGTH {
+                return Err(DerustError::InvalidInput(
+                    format!("audit log reason must be at most {} characters long", MAX_REASON_LENGTH)
+                ));
+            }
+            self.reason = Some(utf8_percent_encode(reason, NON_ALPHANUMERIC).to_string());
+        }
+        Ok(self)
+    }
+
+    /// Send `body` as JSON along with the request.
+    pub fn json<T: Serialize>(mut self, body: &T) -> Result<Self, DerustError> {
+        self.json = Some(serde_json::to_vec(body)
+            .map_err(|e| DerustError::InvalidInput(e.to_string()))?);
+        Ok(self)
+    }
+
+    /// Add a parameter to the query string, if it has a value.
+    pub fn query<T: ToString>(mut self, key: &'static str, value: Option<T>) -> Self {
+        if let Some(value) = value {
+            self.query.push((key, value.to_string()));
+        }
+        self
+    }
+
+    /// The form to send the files and JSON body of
+    /// the request in, built again for every attempt.
+    fn multipart_form(&self) -> reqwest::multipart::Form {
+        let mut form = reqwest::multipart::Form::new();
+        for (i, file) in self.files.iter().enumerate() {
+            let part = reqwest::multipart::Part::bytes(file.data.clone())
+                .file_name(file.filename.clone());
+            form = form.part(format!("file{}", i), part);
+        }
+        if let Some(json) = &self.json {
+            form = form.text("payload_json", String::from_utf8_lossy(json).into_owned());
+        }
+        form
+    }
+}
+
+/// The REST API client, authenticated as a bot.
+///
+/// It can be cloned freely, every clone sharing
+/// the same connection pool and ratelimits.
+#[derive(Clone, Debug)]
+pub struct Http {
+    client: reqwest::Client,
+    base_url: String,
+    max_retries: u32,
+    ratelimiter: Arc<Ratelimiter>,
+    /// Set when the permissions are checked before the requests.
+    #[cfg(feature = "cache")]
+    preflight: Option<Arc<preflight::Preflight>>,
+}
+
+/// How many times a request is retried by default
+/// after being ratelimited or failing on Discord's side.
+const MAX_RETRIES: u32 = 3;
+
+/// How long to wait before retrying a request that failed
+/// with a server error, doubled for every retry.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+impl Http {
+    /// Construct a new client authenticated with the bot `token`,
+    /// without the `Bot ` prefix.
+    pub fn new(token: &str) -> Result<Self, DerustError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bot {}", token))
+            .map_err(|_| DerustError::InvalidToken)?);
+        Self::with_headers(headers)
+    }
+
+    /// Construct a client without a token, for the endpoints
+    /// authenticated by their path such as webhooks.
+    pub(crate) fn unauthenticated() -> Result<Self, DerustError> {
+        Self::with_headers(HeaderMap::new())
+    }
+
+    fn with_headers(mut headers: HeaderMap) -> Result<Self, DerustError> {
+        // get the reset times with a millisecond precision.
+        headers.insert("X-RateLimit-Precision", HeaderValue::from_static("millisecond"));
+        let client = reqwest::Client::builder()
+            .user_agent("DiscordBot (https://github.com/keksbg/derust, 0.1)")
+            .default_headers(headers)
+            .build()?;
+        Ok(Http {
+            client,
+            base_url: crate::API_URL.to_owned(),
+            max_retries: MAX_RETRIES,
+            ratelimiter: Arc::new(Ratelimiter::new()),
+            #[cfg(feature = "cache")]
+            preflight: None,
+        })
+    }
+
+    /// Set the URL of the API requests are sent to, such
+    /// as a proxy or a mock server to test against.
+    ///
+    /// Default: the `API_URL` defined in `lib.rs`.
+    pub fn base_url(&mut self, url: String) -> &mut Self {
+        self.base_url = url;
+        self
+    }
+
+    /// Set how many times a request is retried after getting
+    /// ratelimited or a server error, before giving up and
+    /// returning the error.
+    ///
+    /// Default: 3
+    pub fn max_retries(&mut self, retries: u32) -> &mut Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Make `request`, deserializing the response into `T`.
+    pub async fn request<T: DeserializeOwned>(&self, request: Request) -> Result<T, DerustError> {
+        parse_response(self.send(request).await?).await
+    }
+
+    /// Make `request`, for the endpoints that don't return anything.
+    pub async fn request_empty(&self, request: Request) -> Result<(), DerustError> {
+        let resp = self.send(request).await?;
+        if !resp.status().is_success() {
+            return Err(DerustError::from_response(resp).await);
+        }
+        Ok(())
+    }
+
+    /// Make `request` once its ratelimit bucket allows it, retrying
+    /// it when getting ratelimited anyway or a server error.
+    async fn send(&self, request: Request) -> Result<reqwest::Response, DerustError> {
+        let route = &request.route;
+        let mut attempt = 0;
+        loop {
+            let mut bucket = self.ratelimiter.acquire(route).await;
+            let mut builder = self.client
+                .request(route.method.clone(), &format!("{}{}", self.base_url, route.path))
+                .query(&request.query);
+            if let Some(reason) = &request.reason {
+                builder = builder.header("X-Audit-Log-Reason", reason.as_str());
+            }
+            if !request.files.is_empty() {
+                builder = builder.multipart(request.multipart_form());
+            } else if let Some(json) = &request.json {
+                builder = builder
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(json.clone());
+            } else if route.method != Method::GET {
+                // the API wants a length even without a body.
+                builder = builder.header(reqwest::header::CONTENT_LENGTH, 0);
+            }
+            let resp = builder.send().await?;
+            self.ratelimiter.update(route, &mut bucket, resp.headers());
+            let status = resp.status();
+            let retries_left = attempt < self.max_retries;
+            attempt += 1;
+
+            if status == StatusCode::TOO_MANY_REQUESTS {
+                let e = DerustError::from_response(resp).await;
+                let (retry_after, global) = match &e {
+                    DerustError::Ratelimited { retry_after, global, .. } => (*retry_after, *global),
+                    _ => return Err(e),
+                };
+                let retry_after = Duration::from_millis(retry_after);
+                if global {
+                    warn!("Hit the global ratelimit on {}, pausing requests for {:?}", route.key, retry_after);
+                    self.ratelimiter.set_global(retry_after);
+                } else {
+                    warn!("Hit a ratelimit on {}, retrying after {:?}", route.key, retry_after);
+                }
+                drop(bucket);
+                if !retries_left {
+                    return Err(e);
+                }
+                delay_for(retry_after).await;
+            } else if status.is_server_error() && retries_left {
+                drop(bucket);
+                let delay = RETRY_DELAY * 2u32.saturating_pow(attempt - 1);
+                warn!("{} returned {}, retrying after {:?}", route.key, status, delay);
+                delay_for(delay).await;
+            } else {
+                return Ok(resp);
+            }
+        }
+    }
+
+    /// Get the user the bot is logged in as.
+    pub async fn current_user(&self) -> Result<User, DerustError> {
+        self.request(Request::new(Route::new(Method::GET, String::from("/users/@me")))).await
+    }
+}
+
+/// Without the cache there is nothing to check the permissions against.
+#[cfg(not(feature = "cache"))]
+impl Http {
+    pub(crate) fn require_guild_permissions(
+        &self,
+        _guild_id: crate::types::Snowflake,
+        _required: enumflags2::BitFlags<crate::types::permission::Permissions>,
+    ) -> Result<(), DerustError> {
+        Ok(())
+    }
+
+    pub(crate) fn require_channel_permissions(
+        &self,
+        _channel_id: crate::types::Snowflake,
+        _required: enumflags2::BitFlags<crate::types::permission::Permissions>,
+    ) -> Result<(), DerustError> {
+        Ok(())
+    }
+
+    pub(crate) fn require_above_member(
+        &self,
+        _guild_id: crate::types::Snowflake,
+        _user_id: crate::types::Snowflake,
+    ) -> Result<(), DerustError> {
+        Ok(())
+    }
+
+    pub(crate) fn require_above_role(
+        &self,
+        _guild_id: crate::types::Snowflake,
+        _role_id: crate::types::Snowflake,
+    ) -> Result<(), DerustError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Instant;
+    use reqwest::Method;
+    use tokio::time::{delay_for, Duration};
+    use crate::types::error::DerustError;
+    use super::{Http, Request, RETRY_DELAY, routing::Route};
+
+    /// The status, headers and body answered by the mock server.
+    type MockResponse = (u16, Vec<(&'static str, &'static str)>, &'static str);
+
+    /// The path of every request received by the mock
+    /// server, along with when it was received.
+    type Received = Arc<Mutex<Vec<(String, Instant)>>>;
+
+    const RATELIMITED: &str = r#"{"message": "You are being rate limited.", "retry_after": 500, "global": false}"#;
+    const GLOBALLY_RATELIMITED: &str = r#"{"message": "You are being rate limited.", "retry_after": 500, "global": true}"#;
+    const RETRY_AFTER: Duration = Duration::from_millis(500);
+
+    /// Start an HTTP server answering every request with what `respond`
+    /// returns for its path and the amount of requests received before it,
+    /// returning its URL along with the requests it receives.
+    fn mock_server<F>(respond: F) -> (String, Received)
+        where F: Fn(&str, usize) -> MockResponse + Send + 'static {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind the mock server");
+        let url = format!("http://{}", listener.local_addr().expect("mock server has no address"));
+        let received = Received::default();
+        let log = received.clone();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(stream.try_clone().expect("failed to clone the stream"));
+                let mut request_line = String::new();
+                if reader.read_line(&mut request_line).is_err() {
+                    continue;
+                }
+                let received_at = Instant::now();
+                let path = request_line.split(' ').nth(1).unwrap_or("").to_owned();
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    if reader.read_line(&mut header).unwrap_or(0) == 0 || header == "\r\n" {
+                        break;
+                    }
+                    if let Some(value) = header.to_ascii_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap_or(0);
+                    }
+                }
+                let _ = reader.by_ref().take(length).read_to_end(&mut Vec::new());
+                let count = {
+                    let mut log = log.lock().expect("mock server log poisoned");
+                    log.push((path.clone(), received_at));
+                    log.len() - 1
+                };
+                let (status, headers, body) = respond(&path, count);
+                let mut response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    status, body.len(),
+                );
+                for (name, value) in headers {
+                    response.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                response.push_str("\r\n");
+                response.push_str(body);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        (url, received)
+    }
+
+    fn client(url: String) -> Http {
+        let mut http = Http::new("token").expect("invalid token");
+        http.base_url(url);
+        http
+    }
+
+    fn get(path: &str) -> Request {
+        Request::new(Route::new(Method::GET, path.to_owned()))
+    }
+
+    /// When the requests to `path` were received.
+    fn arrivals(received: &Received, path: &str) -> Vec<Instant> {
+        received.lock().expect("mock server log poisoned").iter()
+            .filter(|(p, _)| p == path)
+            .map(|(_, at)| *at)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn global_ratelimit_blocks_every_route() {
+        let (url, received) = mock_server(|path, count| match (path, count) {
+            ("/users/@me", 0) => (429, vec![("X-RateLimit-Global", "true")], GLOBALLY_RATELIMITED),
+            _ => (200, vec![], "{}"),
+        });
+        let http = client(url);
+        let ratelimited = tokio::spawn({
+            let http = http.clone();
+            async move { http.request_empty(get("/users/@me")).await }
+        });
+        delay_for(Duration::from_millis(100)).await;
+        http.request_empty(get("/gateway")).await.expect("request failed");
+        ratelimited.await.expect("request panicked").expect("request failed");
+
+        let first = arrivals(&received, "/users/@me")[0];
+        let other = arrivals(&received, "/gateway")[0];
+        assert!(other.duration_since(first) >= RETRY_AFTER);
+        assert_eq!(arrivals(&received, "/users/@me").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn global_ratelimit_blocks_requests_queued_in_its_bucket() {
+        let (url, received) = mock_server(|_, count| match count {
+            0 => {
+                // the second request gets queued in the meantime.
+                thread::sleep(Duration::from_millis(200));
+                (429, vec![("X-RateLimit-Global", "true")], GLOBALLY_RATELIMITED)
+            },
+            _ => (200, vec![], "{}"),
+        });
+        let http = client(url);
+        let ratelimited = tokio::spawn({
+            let http = http.clone();
+            async move { http.request_empty(get("/users/@me")).await }
+        });
+        delay_for(Duration::from_millis(100)).await;
+        http.request_empty(get("/users/@me")).await.expect("request failed");
+        ratelimited.await.expect("request panicked").expect("request failed");
+
+        let attempts = arrivals(&received, "/users/@me");
+        assert_eq!(attempts.len(), 3);
+        for later in &attempts[1..] {
+            assert!(later.duration_since(attempts[0]) >= RETRY_AFTER);
+        }
+    }
+
+    #[tokio::test]
+    async fn bucket_ratelimit_only_delays_its_bucket() {
+        let exhausted = vec![("X-RateLimit-Remaining", "0"), ("X-RateLimit-Reset-After", "0.5")];
+        let (url, received) = mock_server(move |path, count| match (path, count) {
+            ("/channels/1/messages", 0) => (429, exhausted.clone(), RATELIMITED),
+            _ => (200, vec![], "{}"),
+        });
+        let http = client(url);
+        let ratelimited = tokio::spawn({
+            let http = http.clone();
+            async move { http.request_empty(get("/channels/1/messages")).await }
+        });
+        delay_for(Duration::from_millis(100)).await;
+        http.request_empty(get("/channels/2/messages")).await.expect("request failed");
+        http.request_empty(get("/channels/1/messages")).await.expect("request failed");
+        ratelimited.await.expect("request panicked").expect("request failed");
+
+        let same_bucket = arrivals(&received, "/channels/1/messages");
+        let other_bucket = arrivals(&received, "/channels/2/messages")[0];
+        assert!(other_bucket.duration_since(same_bucket[0]) < RETRY_AFTER);
+        assert_eq!(same_bucket.len(), 3);
+        for later in &same_bucket[1..] {
+            assert!(later.duration_since(same_bucket[0]) >= RETRY_AFTER);
+        }
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried_with_growing_delays() {
+        let (url, received) = mock_server(|_, _| {
+            (500, vec![], r#"{"message": "500: Internal Server Error", "code": 0}"#)
+        });
+        let mut http = client(url);
+        http.max_retries(2);
+        let result = http.request_empty(get("/users/@me")).await;
+        assert!(matches!(result, Err(DerustError::ApiError { status: 500, .. })));
+
+        let attempts = arrivals(&received, "/users/@me");
+        assert_eq!(attempts.len(), 3);
+        assert!(attempts[1].duration_since(attempts[0]) >= RETRY_DELAY);
+        assert!(attempts[2].duration_since(attempts[1]) >= RETRY_DELAY * 2);
+    }
+
+    #[tokio::test]
+    async fn routes_in_the_same_bucket_share_its_ratelimit() {
+        // the third response exhausts the bucket both routes are in.
+        let (url, received) = mock_server(|_, count| {
+            let remaining = if count < 2 { "1" } else { "0" };
+            let headers = vec![
+                ("X-RateLimit-Bucket", "messages"),
+                ("X-RateLimit-Remaining", remaining),
+                ("X-RateLimit-Reset-After", "0.5"),
+            ];
+            (200, headers, "{}")
+        });
+        let http = client(url);
+        for path in &["/channels/1/messages", "/channels/1/pins", "/channels/1/messages", "/channels/1/pins"] {
+            http.request_empty(get(path)).await.expect("request failed");
+        }
+
+        let exhausted = arrivals(&received, "/channels/1/messages")[1];
+        let next = arrivals(&received, "/channels/1/pins")[1];
+        assert!(next.duration_since(exhausted) >= RETRY_AFTER);
+    }
+}
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+use enumflags2::BitFlags;
+use crate::types::Snowflake;
+use crate::types::error::DerustError;
+use crate::types::gateway::cache::Caches;
+use crate::types::gateway::payloads::GuildType;
+use crate::types::guild::{Guild, GuildMember};
+use crate::types::permission::{self, Permissions};
+use super::Http;
+
+/// What the pre-flight checks of [`Http::check_permissions`]
+/// are made with.
+#[derive(Debug)]
+pub(crate) struct Preflight {
+    caches: Caches,
+    /// The ID of the bot.
+    user_id: Snowflake,
+}
+
+impl Preflight {
+    /// The member `user_id` of the guild `guild_id`, if it is cached.
+    fn member(&self, guild_id: Snowflake, user_id: Snowflake) -> Option<GuildMember> {
+        let cache = self.caches.members()?.lock().expect("member cache poisoned");
+        cache.get(guild_id)?.get(&user_id).cloned()
+    }
+
+    /// Run `f` with the cached guild and the member of the bot
+    /// in it, if they are both cached.
+    fn with_guild<T, F>(&self, guild_id: Snowflake, f: F) -> Option<T>
+        where F: FnOnce(&Guild, &GuildMember) -> T {
+        // cloned so that the member cache isn't locked
+        // along with the guild cache.
+        let me = self.member(guild_id, self.user_id)?;
+        let cache = self.caches.guilds()?.lock().expect("guild cache poisoned");
+        let guild = match cache.get(guild_id)? {
+            GuildType::Full(guild) => guild,
+            GuildType::Partial(_) => return None,
+        };
+        Some(f(guild, &me))
+    }
+}
+
+/// The position of the highest role of `member`,
+/// `@everyone` being at the bottom.
+fn highest_position(guild: &Guild, member: &GuildMember) -> i32 {
+    guild.roles.iter()
+        .filter(|r| member.roles.contains(&r.id))
+        .map(|r| r.position)
+        .max()
+        .unwrap_or(0)
+}
+
+fn require(permissions: BitFlags<Permissions>, required: BitFlags<Permissions>) -> Result<(), DerustError> {
+    let missing = required & !permissions;
+    if !missing.is_empty() {
+        return Err(DerustError::MissingPermissions { missing });
+    }
+    Ok(())
+}
+
+impl Http {
+    /// Check the permissions of the bot in the cached guilds and
+    /// channels before making the requests that need them, returning
+    /// [`DerustError::MissingPermissions`] instead of sending requests
+    /// the API would reject, or [`DerustError::RoleHierarchy`] for the
+    /// members and roles the bot is not above.
+    ///
+    /// `user_id` is the ID of the bot, as given in the `Ready` event
+    /// or by [`current_user`](#method.current_user). Requests are still
+    /// sent when what they would be checked against isn't cached.
+    ///
+    /// Default: disabled.
+    pub fn check_permissions(&mut self, caches: Caches, user_id: Snowflake) -> &mut Self {
+        self.preflight = Some(Arc::new(Preflight { caches, user_id }));
+        self
+    }
+
+    pub(crate) fn require_guild_permissions(
+        &self,
+        guild_id: Snowflake,
+        required: BitFlags<Permissions>,
+    ) -> Result<(), DerustError> {
+        let preflight = match &self.preflight {
+            Some(preflight) => preflight,
+            None => return Ok(()),
+        };
+        preflight.with_guild(guild_id, |guild, me| {
+            require(permission::guild_permissions(guild, me), required)
+        }).unwrap_or(Ok(()))
+    }
+
+    pub(crate) fn require_channel_permissions(
+        &self,
+        channel_id: Snowflake,
+        required: BitFlags<Permissions>,
+    ) -> Result<(), DerustError> {
+        let preflight = match &self.preflight {
+            Some(preflight) => preflight,
+            None => return Ok(()),
+        };
+        // cloned so that the channel cache isn't locked
+        // along with the guild cache.
+        let channel = preflight.caches.channels().and_then(|cache| {
+            cache.lock().expect("channel cache poisoned").get(channel_id).cloned()
+        });
+        // private channels have no permissions.
+        let (channel, guild_id) = match channel {
+            Some(channel) => match channel.guild_id {
+                Some(guild_id) => (channel, guild_id),
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        preflight.with_guild(guild_id, |guild, me| {
+            require(permission::channel_permissions(guild, me, &channel), required)
+        }).unwrap_or(Ok(()))
+    }
+
+    /// Check that the highest role of the member `user_id`
+    /// is below the one of the bot.
+    pub(crate) fn require_above_member(
+        &self,
+        guild_id: Snowflake,
+        user_id: Snowflake,
+    ) -> Result<(), DerustError> {
+        let preflight = match &self.preflight {
+            Some(preflight) => preflight,
+            None => return Ok(()),
+        };
+        let target = preflight.member(guild_id, user_id);
+        preflight.with_guild(guild_id, |guild, me| {
+            if preflight.user_id == guild.owner_id {
+                return Ok(());
+            }
+            let above = user_id != guild.owner_id && match &target {
+                Some(target) => highest_position(guild, target) < highest_position(guild, me),
+                None => true,
+            };
+            if !above {
+                return Err(DerustError::RoleHierarchy { target: user_id });
+            }
+            Ok(())
+        }).unwrap_or(Ok(()))
+    }
+
+    /// Check that the role `role_id` is below
+    /// the highest role of the bot.
+    pub(crate) fn require_above_role(
+        &self,
+        guild_id: Snowflake,
+        role_id: Snowflake,
+    ) -> Result<(), DerustError> {
+        let preflight = match &self.preflight {
+            Some(preflight) => preflight,
+            None => return Ok(()),
+        };
+        preflight.with_guild(guild_id, |guild, me| {
+            if preflight.user_id == guild.owner_id {
+                return Ok(());
+            }
+            let above = match guild.roles.iter().find(|r| r.id == role_id) {
+                Some(role) => role.position < highest_position(guild, me),
+                None => true,
+            };
+            if !above {
+                return Err(DerustError::RoleHierarchy { target: role_id });
+            }
+            Ok(())
+        }).unwrap_or(Ok(()))
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex};
+use reqwest::header::HeaderMap;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tokio::time::{delay_until, Duration, Instant};
+use tracing::debug;
+use super::routing::Route;
+
+/// The state of a ratelimit bucket, as last told by the API.
+#[derive(Debug, Default)]
+pub(crate) struct BucketState {
+    /// Requests left until `reset_at`, unknown until
+    /// the first response in this bucket.
+    remaining: Option<u32>,
+    reset_at: Option<Instant>,
+}
+
+/// Requests in a bucket wait for the lock of the bucket
+/// in order, and keep it until their response arrives.
+pub(crate) type Bucket = Arc<Mutex<BucketState>>;
+
+/// Keeps track of the ratelimit buckets of the API.
+///
+/// Routes get their own bucket until the API tells us which
+/// bucket they are in with `X-RateLimit-Bucket`, from then on
+/// routes in the same bucket with the same major parameter
+/// share it.
+#[derive(Debug, Default)]
+pub(crate) struct Ratelimiter {
+    /// Bucket ID told by the API for each route key.
+    routes: StdMutex<HashMap<String, String>>,
+    /// Buckets by bucket ID and major parameter, or by route key
+    /// for the routes whose bucket ID isn't known yet.
+    buckets: StdMutex<HashMap<String, Bucket>>,
+    /// When the global ratelimit we hit ends, if we did.
+    global_reset: StdMutex<Option<Instant>>,
+}
+
+impl Ratelimiter {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// The key of the bucket `route` is counted in.
+    fn bucket_key(&self, route: &Route) -> String {
+        let routes = self.routes.lock().expect("ratelimit routes poisoned");
+        match routes.get(&route.key) {
+            Some(id) => format!("{}:{}", id, route.major_parameter().unwrap_or("")),
+            None => route.key.clone(),
+        }
+    }
+
+    fn bucket(&self, key: String) -> Bucket {
+        self.buckets.lock().expect("ratelimit buckets poisoned")
+            .entry(key)
+            .or_default()
+            .clone()
+    }
+
+    /// Pause every request for `retry_after`, after
+    /// hitting the global ratelimit.
+    pub(crate) fn set_global(&self, retry_after: Duration) {
+        let reset_at = Instant::now() + retry_after;
+        let mut global_reset = self.global_reset.lock().expect("global ratelimit poisoned");
+        *global_reset = Some(global_reset.map_or(reset_at, |r| r.max(reset_at)));
+    }
+
+    /// When the global ratelimit we hit ends, if it hasn't yet.
+    fn global_reset(&self) -> Option<Instant> {
+        let global_reset = *self.global_reset.lock().expect("global ratelimit poisoned");
+        global_reset.filter(|reset_at| *reset_at > Instant::now())
+    }
+
+    /// Wait until a request can be made to `route` without being
+    /// ratelimited, returning the guard to keep until its response
+    /// has been given to [`update`](#method.update).
+    pub(crate) async fn acquire(&self, route: &Route) -> BucketGuard {
+        let bucket = self.bucket(self.bucket_key(route));
+        let guard = bucket.lock_owned().await;
+        // the global ratelimit may be hit by another request while
+        // we wait, so both are checked until neither makes us wait.
+        loop {
+            if let Some(reset_at) = self.global_reset() {
+                debug!("Globally ratelimited, waiting {:?}", reset_at - Instant::now());
+                delay_until(reset_at).await;
+                continue;
+            }
+            if let (Some(0), Some(reset_at)) = (guard.remaining, guard.reset_at) {
+                if reset_at > Instant::now() {
+                    debug!("Bucket of {} is exhausted, waiting {:?}", route.key, reset_at - Instant::now());
+                    delay_until(reset_at).await;
+                    continue;
+                }
+            }
+            break;
+        }
+        BucketGuard { guard }
+    }
+
+    /// Update the bucket of `route` from the ratelimit
+    /// headers of the response to a request made to it.
+    ///
+    /// The guard is still held afterwards, so that a global
+    /// ratelimit in the response can be set before the next
+    /// request in the bucket goes.
+    pub(crate) fn update(&self, route: &Route, bucket: &mut BucketGuard, headers: &HeaderMap) {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        if let Some(remaining) = header("X-RateLimit-Remaining").and_then(|v| v.parse().ok()) {
+            bucket.guard.remaining = Some(remaining);
+        }
+        if let Some(reset_after) = header("X-RateLimit-Reset-After").and_then(|v| v.parse::<f64>().ok()) {
+            bucket.guard.reset_at = Some(Instant::now() + Duration::from_secs_f64(reset_after.max(0.0)));
+        }
+        if let Some(id) = header("X-RateLimit-Bucket") {
+            let mut routes = self.routes.lock().expect("ratelimit routes poisoned");
+            if routes.get(&route.key).map(String::as_str) != Some(id) {
+                routes.insert(route.key.clone(), id.to_owned());
+                drop(routes);
+                // the requests that come next use the shared bucket,
+                // which starts out as this one.
+                let key = self.bucket_key(route);
+                let shared = self.bucket(key);
+                let locked = shared.try_lock();
+                if let Ok(mut state) = locked {
+                    if state.remaining.is_none() {
+                        state.remaining = bucket.guard.remaining;
+                        state.reset_at = bucket.guard.reset_at;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Held while a request is being made in a bucket,
+/// so that the requests after it wait for its response.
+pub(crate) struct BucketGuard {
+    guard: OwnedMutexGuard<BucketState>,
+}
@@ -0,0 +1,93 @@
+use reqwest::Method;
+use crate::types::Snowflake;
+use crate::types::error::DerustError;
+use crate::types::message::ReactionType;
+use crate::types::permission::Permissions;
+use crate::types::user::User;
+use super::{Http, Request, routing::Route, validate_limit};
+
+/// The path of the reactions of a message with `emoji`.
+fn reactions_path(channel_id: Snowflake, message_id: Snowflake, emoji: &ReactionType) -> String {
+    format!("/channels/{}/messages/{}/reactions/{}", channel_id, message_id, emoji.to_path_segment())
+}
+
+impl Http {
+    /// React to a message with `emoji` as the bot.
+    pub async fn create_reaction(
+        &self,
+        channel_id: Snowflake,
+        message_id: Snowflake,
+        emoji: &ReactionType,
+    ) -> Result<(), DerustError> {
+        let path = format!("{}/@me", reactions_path(channel_id, message_id, emoji));
+        self.request_empty(Request::new(Route::new(Method::PUT, path))).await
+    }
+
+    /// Remove a reaction of the bot.
+    pub async fn delete_own_reaction(
+        &self,
+        channel_id: Snowflake,
+        message_id: Snowflake,
+        emoji: &ReactionType,
+    ) -> Result<(), DerustError> {
+        let path = format!("{}/@me", reactions_path(channel_id, message_id, emoji));
+        self.request_empty(Request::new(Route::new(Method::DELETE, path))).await
+    }
+
+    /// Remove a reaction of another user.
+    pub async fn delete_user_reaction(
+        &self,
+        channel_id: Snowflake,
+        message_id: Snowflake,
+        emoji: &ReactionType,
+        user_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        self.require_channel_permissions(channel_id, Permissions::ManageMessages.into())?;
+        let path = format!("{}/{}", reactions_path(channel_id, message_id, emoji), user_id);
+        self.request_empty(Request::new(Route::new(Method::DELETE, path)).reason(reason)?).await
+    }
+
+    /// Get up to `limit` users who reacted with `emoji`, between 1
+    /// and 100 (default 25), ordered by their ID.
+    ///
+    /// To get the next page, pass the ID of the last user
+    /// of this one as `after`.
+    pub async fn get_reactions(
+        &self,
+        channel_id: Snowflake,
+        message_id: Snowflake,
+        emoji: &ReactionType,
+        after: Option<Snowflake>,
+        limit: Option<u8>,
+    ) -> Result<Vec<User>, DerustError> {
+        validate_limit(limit, 100)?;
+        let route = Route::new(Method::GET, reactions_path(channel_id, message_id, emoji));
+        self.request(Request::new(route).query("after", after).query("limit", limit)).await
+    }
+
+    /// Remove every reaction on a message.
+    pub async fn delete_all_reactions(
+        &self,
+        channel_id: Snowflake,
+        message_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        self.require_channel_permissions(channel_id, Permissions::ManageMessages.into())?;
+        let path = format!("/channels/{}/messages/{}/reactions", channel_id, message_id);
+        self.request_empty(Request::new(Route::new(Method::DELETE, path)).reason(reason)?).await
+    }
+
+    /// Remove every reaction with `emoji` on a message.
+    pub async fn delete_all_reactions_for_emoji(
+        &self,
+        channel_id: Snowflake,
+        message_id: Snowflake,
+        emoji: &ReactionType,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        self.require_channel_permissions(channel_id, Permissions::ManageMessages.into())?;
+        let route = Route::new(Method::DELETE, reactions_path(channel_id, message_id, emoji));
+        self.request_empty(Request::new(route).reason(reason)?).await
+    }
+}
@@ -0,0 +1,60 @@
+use reqwest::Method;
+
+/// The path segments after which comes a major parameter,
+/// which gets its own ratelimit bucket for every value.
+const MAJOR_PARAMETERS: [&str; 3] = ["channels", "guilds", "webhooks"];
+
+/// An endpoint of the API, along with the key of the
+/// ratelimit bucket its requests are counted in.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Route {
+    pub(crate) method: Method,
+    /// Relative to the API URL, such as `/channels/1234/messages`.
+    pub(crate) path: String,
+    /// The method and path, with every ID but the
+    /// major parameter replaced by a placeholder.
+    pub(crate) key: String,
+}
+
+impl Route {
+    pub fn new(method: Method, path: String) -> Self {
+        let mut key = format!("{} ", method);
+        let mut previous = "";
+        let mut major = true;
+        let is_webhook = path.starts_with("/webhooks/");
+        for (i, segment) in path.split('/').filter(|s| !s.is_empty()).enumerate() {
+            key.push('/');
+            if is_webhook && i == 2 {
+                // keep webhook tokens out of the logs.
+                key.push_str(":token");
+            } else if major && MAJOR_PARAMETERS.contains(&previous) {
+                key.push_str(segment);
+                // only the first one counts, so that
+                // `/guilds/1/members/2` shares a bucket with `/guilds/1/members/3`.
+                major = false;
+            } else if previous == "reactions" || is_id(segment) {
+                // emojis are parameters too.
+                key.push_str(":id");
+            } else {
+                key.push_str(segment);
+            }
+            previous = segment;
+        }
+        Route { method, path, key }
+    }
+
+    /// The value of the major parameter of this route, if it has one,
+    /// which is the same for every route sharing one of its buckets.
+    pub(crate) fn major_parameter(&self) -> Option<&str> {
+        let mut segments = self.path.split('/').filter(|s| !s.is_empty());
+        let first = segments.next()?;
+        if !MAJOR_PARAMETERS.contains(&first) {
+            return None;
+        }
+        segments.next()
+    }
+}
+
+fn is_id(segment: &str) -> bool {
+    segment.bytes().all(|b| b.is_ascii_digit())
+}
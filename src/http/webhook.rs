@@ -0,0 +1,273 @@
+use reqwest::Method;
+use crate::types::Snowflake;
+use crate::types::error::DerustError;
+use crate::types::guild::Webhook;
+use crate::types::message::{AllowedMentions, Embed, Message};
+use crate::types::permission::Permissions;
+use super::{Http, Request, channel::validate_content, file::AttachmentFile, routing::Route};
+
+/// How many embeds a webhook message can have.
+const MAX_EMBEDS: usize = 10;
+
+/// A webhook to create in a channel.
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CreateWebhook {
+    /// Between 1 and 80 characters.
+    pub name: String,
+    /// As a data URI, like `data:image/png;base64,...`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<String>,
+}
+
+/// The fields of a webhook to modify, the ones left
+/// to `None` being kept as they are.
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ModifyWebhook {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<String>,
+    /// The channel to move the webhook to, which
+    /// can't be done with its token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<Snowflake>,
+}
+
+/// A message to send with a webhook, which needs at
+/// least either a `content`, `embeds` or `files`.
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ExecuteWebhook {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Shown instead of the name of the webhook.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// Shown instead of the avatar of the webhook.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    pub tts: bool,
+    /// Up to 10 embeds.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub embeds: Vec<Embed>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_mentions: Option<AllowedMentions>,
+    /// Uploaded as attachments of the message.
+    #[serde(skip)]
+    pub files: Vec<AttachmentFile>,
+}
+
+impl ExecuteWebhook {
+    /// A message with only text in it.
+    pub fn new(content: String) -> Self {
+        ExecuteWebhook {
+            content: Some(content),
+            ..Default::default()
+        }
+    }
+
+    fn validate(&self) -> Result<(), DerustError> {
+        if self.content.is_none() && self.embeds.is_empty() && self.files.is_empty() {
+            return Err(DerustError::InvalidInput(
+                String::from("a webhook message needs either a content, embeds or files")
+            ));
+        }
+        validate_embeds(self.embeds.len())?;
+        validate_content(&self.content)
+    }
+}
+
+/// The fields of a message sent by a webhook to edit,
+/// the ones left to `None` being kept as they are.
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct EditWebhookMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embeds: Option<Vec<Embed>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_mentions: Option<AllowedMentions>,
+}
+
+fn validate_embeds(count: usize) -> Result<(), DerustError> {
+    if count > MAX_EMBEDS {
+        return Err(DerustError::InvalidInput(
+            format!("a webhook message can have at most {} embeds, got {}", MAX_EMBEDS, count)
+        ));
+    }
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<(), DerustError> {
+    if !(1..=80).contains(&name.chars().count()) {
+        return Err(DerustError::InvalidInput(
+            String::from("webhook names must be between 1 and 80 characters long")
+        ));
+    }
+    Ok(())
+}
+
+impl Http {
+    pub async fn create_webhook(
+        &self,
+        channel_id: Snowflake,
+        webhook: &CreateWebhook,
+        reason: Option<&str>,
+    ) -> Result<Webhook, DerustError> {
+        validate_name(&webhook.name)?;
+        self.require_channel_permissions(channel_id, Permissions::ManageWebhooks.into())?;
+        let route = Route::new(Method::POST, format!("/channels/{}/webhooks", channel_id));
+        self.request(Request::new(route).reason(reason)?.json(webhook)?).await
+    }
+
+    pub async fn get_channel_webhooks(&self, channel_id: Snowflake) -> Result<Vec<Webhook>, DerustError> {
+        self.require_channel_permissions(channel_id, Permissions::ManageWebhooks.into())?;
+        let route = Route::new(Method::GET, format!("/channels/{}/webhooks", channel_id));
+        self.request(Request::new(route)).await
+    }
+
+    pub async fn get_guild_webhooks(&self, guild_id: Snowflake) -> Result<Vec<Webhook>, DerustError> {
+        self.require_guild_permissions(guild_id, Permissions::ManageWebhooks.into())?;
+        let route = Route::new(Method::GET, format!("/guilds/{}/webhooks", guild_id));
+        self.request(Request::new(route)).await
+    }
+
+    pub async fn get_webhook(&self, webhook_id: Snowflake) -> Result<Webhook, DerustError> {
+        let route = Route::new(Method::GET, format!("/webhooks/{}", webhook_id));
+        self.request(Request::new(route)).await
+    }
+
+    pub async fn modify_webhook(
+        &self,
+        webhook_id: Snowflake,
+        webhook: &ModifyWebhook,
+        reason: Option<&str>,
+    ) -> Result<Webhook, DerustError> {
+        if let Some(name) = &webhook.name {
+            validate_name(name)?;
+        }
+        let route = Route::new(Method::PATCH, format!("/webhooks/{}", webhook_id));
+        self.request(Request::new(route).reason(reason)?.json(webhook)?).await
+    }
+
+    pub async fn delete_webhook(
+        &self,
+        webhook_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        let route = Route::new(Method::DELETE, format!("/webhooks/{}", webhook_id));
+        self.request_empty(Request::new(route).reason(reason)?).await
+    }
+}
+
+/// A client for a single webhook, authenticated by its token
+/// instead of a bot token.
+///
+/// ```rust,no_run
+/// use derust::http::webhook::{ExecuteWebhook, WebhookClient};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let webhook = WebhookClient::from_url("https://discord.com/api/webhooks/1234/token")
+///         .expect("Invalid webhook URL");
+///     webhook.execute(&ExecuteWebhook::new(String::from("Build passed")), false).await
+///         .expect("Failed to execute the webhook");
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct WebhookClient {
+    http: Http,
+    id: Snowflake,
+    token: String,
+}
+
+impl WebhookClient {
+    pub fn new(id: Snowflake, token: String) -> Result<Self, DerustError> {
+        Ok(WebhookClient {
+            http: Http::unauthenticated()?,
+            id,
+            token,
+        })
+    }
+
+    /// Construct a client from the URL of a webhook, like
+    /// `https://discord.com/api/webhooks/{id}/{token}`.
+    pub fn from_url(url: &str) -> Result<Self, DerustError> {
+        let invalid = || DerustError::InvalidInput(String::from("invalid webhook URL"));
+        let path = url.split(&['?', '#'][..]).next().unwrap_or(url);
+        let mut segments = path.split('/').skip_while(|s| *s != "webhooks").skip(1);
+        let id = segments.next().and_then(|id| id.parse().ok()).ok_or_else(invalid)?;
+        let token = segments.next().filter(|token| !token.is_empty()).ok_or_else(invalid)?;
+        Self::new(Snowflake(id), token.to_owned())
+    }
+
+    /// The underlying client, to set its base URL or retries.
+    pub fn http(&mut self) -> &mut Http {
+        &mut self.http
+    }
+
+    fn path(&self) -> String {
+        format!("/webhooks/{}/{}", self.id, self.token)
+    }
+
+    pub async fn get(&self) -> Result<Webhook, DerustError> {
+        self.http.request(Request::new(Route::new(Method::GET, self.path()))).await
+    }
+
+    /// Modify the webhook, which can't be moved to
+    /// another channel without a bot token.
+    pub async fn modify(&self, webhook: &ModifyWebhook) -> Result<Webhook, DerustError> {
+        if webhook.channel_id.is_some() {
+            return Err(DerustError::InvalidInput(
+                String::from("webhooks can't be moved to another channel with their token")
+            ));
+        }
+        if let Some(name) = &webhook.name {
+            validate_name(name)?;
+        }
+        let route = Route::new(Method::PATCH, self.path());
+        self.http.request(Request::new(route).json(webhook)?).await
+    }
+
+    pub async fn delete(&self) -> Result<(), DerustError> {
+        self.http.request_empty(Request::new(Route::new(Method::DELETE, self.path()))).await
+    }
+
+    /// Send a message with the webhook, which is returned
+    /// if `wait` is set, once it has been sent.
+    pub async fn execute(
+        &self,
+        message: &ExecuteWebhook,
+        wait: bool,
+    ) -> Result<Option<Message>, DerustError> {
+        message.validate()?;
+        let request = Request::new(Route::new(Method::POST, self.path()))
+            .query("wait", Some(wait))
+            .json(message)?
+            .files(message.files.clone())?;
+        if wait {
+            self.http.request(request).await.map(Some)
+        } else {
+            self.http.request_empty(request).await.map(|_| None)
+        }
+    }
+
+    /// Edit a message sent by the webhook.
+    pub async fn edit_message(
+        &self,
+        message_id: Snowflake,
+        message: &EditWebhookMessage,
+    ) -> Result<Message, DerustError> {
+        validate_content(&message.content)?;
+        if let Some(embeds) = &message.embeds {
+            validate_embeds(embeds.len())?;
+        }
+        let route = Route::new(Method::PATCH, format!("{}/messages/{}", self.path(), message_id));
+        self.http.request(Request::new(route).json(message)?).await
+    }
+
+    /// Delete a message sent by the webhook.
+    pub async fn delete_message(&self, message_id: Snowflake) -> Result<(), DerustError> {
+        let route = Route::new(Method::DELETE, format!("{}/messages/{}", self.path(), message_id));
+        self.http.request_empty(Request::new(route)).await
+    }
+}
@@ -5,24 +5,32 @@
 //!
 //! # Minimalist Example
 //!
-//! ```rust
-//! use derust::types::gateway::builder::IdentifyObject;
+//! ```no_run
+//! use derust::types::gateway::builder::{IdentifyObject, Intents};
 //! use derust::types::gateway::client::Client;
 //! use derust::types::gateway::cache::Caches;
+//! use derust::types::gateway::activity::{Activity, ActivityType, Status, StatusUpdate};
 //! use enumflags2::BitFlags;
 //!
 //! #[tokio::main]
 //! async fn main() {
-//! let io = IdentifyObject::new(String::from("token"))
-//!         .intents(BitFlags::from_bits(0b110010).unwrap().bits()) // guilds, guild members, guild integrations
+//!     let mut io = IdentifyObject::new(String::from("token"));
+//!     io.intents(BitFlags::<Intents>::from_bits(0b110010).unwrap().bits().into()) // guilds, guild members, guild integrations
 //!         .compress(true)
+//!         .guild_subscriptions(false)
 //!         .large_threshold(250)
-//!         .guild_subscriptions(false);
+//!         .expect("large_threshold must be between 50 and 250");
 //!
-//!     let cache = Caches::initialize();
+//!     let cache = Caches::initialize().await;
 //!
-//!     let ws = Client::new(io, cache)
-//!         .login();
+//!     let (shard, mut ws) = Client::new(io, cache).await
+//!         .expect("Failed to create the client")
+//!         .login().await;
+//!
+//!     let _ = shard.update_presence(StatusUpdate::new(
+//!         Activity::new(String::from("with derust"), ActivityType::Game),
+//!         Status::Online,
+//!     )).await;
 //!
 //!     while let Some(e) = ws.recv().await {
 //!         match e {
@@ -41,19 +49,28 @@
 //! mostly by using `match` on the events that are sent back
 //! by the [`Client`] via a [`tokio::sync::mpsc`](https://docs.rs/tokio/0.2.22/tokio/sync/mpsc/fn.channel.html).
 //!
+//! Errors are sent over the same channel as an `Err`, such as payloads
+//! that failed to be decoded. If it is one the [`Client`] cannot recover
+//! from by itself, such as the gateway closing the connection because
+//! of an invalid token, the [`Client`] shuts down right after.
+//!
 //! [`Client`]: types::gateway::client::Client
 //!
 //! # Crate Features
 //! * Default: `cache`
 //!
 //! * `cache`: Utilize the built-in cache with
-//! the [`std::collections::HashMap`] type, or specify your own.
+//!   the [`std::collections::HashMap`] type, or specify your own.
 //!
 //! # Caching
 //! If you are just utilizing the default features, you will
 //! be using an unlimited [`std::collections::HashMap`] to store the
 //! cached elements.
 //!
+//! The caches are updated from the events of the gateway before
+//! they are sent to you, so that they already include the changes
+//! an event is about when you receive it.
+//!
 //! While it is theoretically fast as it is [`O(1)`](https://en.wikipedia.org/wiki/Big_O_notation)
 //! to insert, get and remove elements, you might want to use a
 //! separate service like Redis or your own type, for that you have to make
@@ -65,19 +82,27 @@
 //! you can just disable the default features of the crate, or disable
 //! the caching of individual elements when constructing the [`Client`]
 //! with the functions `TYPE_cache()` and specifying `None`.
-//! ```rust
-//! Client::new(
-//!     IdentifyObject::new("token")
-//! ).channel_cache(None).login()
+//! ```no_run
+//! # use derust::types::gateway::builder::IdentifyObject;
+//! # use derust::types::gateway::client::Client;
+//! # use derust::types::gateway::cache::Caches;
+//! # async fn run() -> Result<(), derust::types::error::DerustError> {
+//! let mut caches = Caches::initialize().await;
+//! caches.channel_cache(None).await;
+//! let (shard, events) = Client::new(
+//!     IdentifyObject::new(String::from("token")), caches
+//! ).await?.login().await;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! [`Client`]: types::gateway::client::Client
-#![feature(in_band_lifetimes)]
 #![allow(dead_code)]
 //#![warn(missing_docs)] not now, lol (warning: 263 warnings emitted)
 #[macro_use] extern crate serde;
 
 pub mod types;
+pub mod http;
 mod helpers;
 const API_URL: &str = "https://discord.com/api/v6";
 const GATEWAY_URL: &str = "wss://gateway.discord.gg";
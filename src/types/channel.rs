@@ -1,8 +1,13 @@
 use super::user::User;
 use super::permission::PermissionOverwrite;
 use serde::{Serialize, Deserialize};
+use serde_repr::{Serialize_repr, Deserialize_repr};
 use crate::types::Snowflake;
 use super::CachedTypes;
+use super::error::DerustError;
+use super::message::Message;
+use crate::http::Http;
+use crate::http::channel::{CreateMessage, MessagesPosition, ModifyChannel};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct Channel {
@@ -29,9 +34,61 @@ pub struct Channel {
 
 impl CachedTypes for Channel {}
 
+impl Channel {
+    /// See [`Http::get_messages`].
+    pub async fn messages(
+        &self,
+        http: &Http,
+        position: Option<MessagesPosition>,
+        limit: Option<u8>,
+    ) -> Result<Vec<Message>, DerustError> {
+        http.get_messages(self.id, position, limit).await
+    }
+
+    pub async fn send_message(&self, http: &Http, message: &CreateMessage) -> Result<Message, DerustError> {
+        http.create_message(self.id, message).await
+    }
+
+    /// Send a message with only text in it.
+    pub async fn say(&self, http: &Http, content: String) -> Result<Message, DerustError> {
+        http.create_message(self.id, &CreateMessage::new(content)).await
+    }
+
+    pub async fn modify(
+        &self,
+        http: &Http,
+        channel: &ModifyChannel,
+        reason: Option<&str>,
+    ) -> Result<Channel, DerustError> {
+        http.modify_channel(self.id, channel, reason).await
+    }
+
+    pub async fn delete(&self, http: &Http, reason: Option<&str>) -> Result<Channel, DerustError> {
+        http.delete_channel(self.id, reason).await
+    }
+
+    pub async fn bulk_delete(
+        &self,
+        http: &Http,
+        message_ids: &[Snowflake],
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        http.bulk_delete_messages(self.id, message_ids, reason).await
+    }
+
+    pub async fn trigger_typing(&self, http: &Http) -> Result<(), DerustError> {
+        http.trigger_typing(self.id).await
+    }
+
+    pub async fn pins(&self, http: &Http) -> Result<Vec<Message>, DerustError> {
+        http.get_pinned_messages(self.id).await
+    }
+}
+
 /// All the different channel types that correspond
 /// to the integer returned by the Discord API
-#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub enum ChannelType {
     /// Text Channel in a Guild
     GuildText = 0,
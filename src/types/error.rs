@@ -1,9 +1,208 @@
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use std::fmt;
+use enumflags2::BitFlags;
+use super::Snowflake;
+use super::gateway::opcodes::{CloseCode, OPCode};
+use super::permission::Permissions;
+
+/// How many characters of the offending payload
+/// are kept in a [`DerustError::DecodeError`].
+const SNIPPET_LENGTH: usize = 200;
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub enum DerustError {
-    UnknownError,
-    HttpError,
-    NoPermissions,
+    /// A request could not be sent, or its response could not
+    /// be read, such as when the connection to the API failed.
+    HttpError(String),
+    /// The API answered a request with an error `status`, along
+    /// with the error object in its body, if there was one.
+    ApiError {
+        status: u16,
+        error: Option<DiscordError>,
+    },
+    /// The bot is `missing` permissions needed for a request,
+    /// as found by the pre-flight checks before sending it.
+    MissingPermissions {
+        missing: BitFlags<Permissions>,
+    },
+    /// The bot can't act on `target`, a member or a role, which
+    /// isn't below the highest role of the bot, as found by the
+    /// pre-flight checks before sending a request.
+    RoleHierarchy {
+        target: Snowflake,
+    },
     InvalidToken,
+    DecompressError,
+    /// Starting the shards would use more session starts than
+    /// we have `remaining`, which get reset after `reset_after`
+    /// milliseconds.
+    SessionStartLimit {
+        remaining: u32,
+        reset_after: u64,
+    },
+    /// A payload received from the gateway or the API could not be
+    /// deserialized, `op` and `t` being the ones of that payload
+    /// if it came from the gateway and they could be read.
+    ///
+    /// `snippet` is the part of the payload where it failed.
+    DecodeError {
+        op: Option<OPCode>,
+        t: Option<String>,
+        message: String,
+        snippet: String,
+    },
+    /// The gateway closed the connection with a code
+    /// after which we can't reconnect.
+    GatewayClosed(CloseCode),
+    /// The gateway invalidated the session, and the client
+    /// was told not to identify again by itself.
+    SessionInvalidated,
+    /// We hit a ratelimit, and may only retry after `retry_after`
+    /// milliseconds. If it is `global`, it applies to every
+    /// request, otherwise only to the ones in `bucket`.
+    Ratelimited {
+        retry_after: u64,
+        global: bool,
+        bucket: Option<String>,
+    },
+    /// A value was rejected before anything was sent,
+    /// because Discord wouldn't accept it either.
+    InvalidInput(String),
+    /// A command could not be queued because
+    /// the client it was sent to has shut down.
+    ClientShutDown,
+    /// The gateway didn't answer a request in time.
+    Timeout,
+}
+
+/// The error object sent by the API along with an error status.
+///
+/// See the [Discord developer documentation](https://discord.com/developers/docs/topics/opcodes-and-status-codes#json)
+/// for what each `code` means.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct DiscordError {
+    pub code: u32,
+    pub message: String,
+    /// The fields of the request that were invalid, nested
+    /// the same way as in the body that was sent.
+    #[serde(default)]
+    pub errors: Option<serde_json::Value>,
+}
+
+/// The body of a 429 response.
+#[derive(Deserialize)]
+struct RatelimitBody {
+    /// In milliseconds, but sent as a float.
+    retry_after: f64,
+    #[serde(default)]
+    global: bool,
+}
+
+impl DerustError {
+    /// A [`DerustError::DecodeError`] for `text`, keeping
+    /// the part of it where deserializing failed.
+    pub(crate) fn decode(
+        op: Option<OPCode>,
+        t: Option<String>,
+        text: &str,
+        e: &serde_json::Error,
+    ) -> Self {
+        // payloads are sent on a single line, so the
+        // column is enough to tell where it failed.
+        let start = e.column().saturating_sub(SNIPPET_LENGTH / 2);
+        DerustError::DecodeError {
+            op,
+            t,
+            message: e.to_string(),
+            snippet: text.chars().skip(start).take(SNIPPET_LENGTH).collect(),
+        }
+    }
+
+    /// Read the error out of a response with an error status.
+    pub(crate) async fn from_response(resp: reqwest::Response) -> Self {
+        let status = resp.status();
+        let bucket = resp.headers().get("X-RateLimit-Bucket")
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        let body = match resp.text().await {
+            Ok(body) => body,
+            Err(e) => return e.into(),
+        };
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            if let Ok(r) = serde_json::from_str::<RatelimitBody>(&body) {
+                return DerustError::Ratelimited {
+                    retry_after: r.retry_after.ceil() as u64,
+                    global: r.global,
+                    bucket,
+                };
+            }
+        }
+        DerustError::ApiError {
+            status: status.as_u16(),
+            error: serde_json::from_str(&body).ok(),
+        }
+    }
+}
+
+/// Deserialize the body of a response into `T`,
+/// or read the error out of it if it has an error status.
+pub(crate) async fn parse_response<T: DeserializeOwned>(
+    resp: reqwest::Response,
+) -> Result<T, DerustError> {
+    if !resp.status().is_success() {
+        return Err(DerustError::from_response(resp).await);
+    }
+    let body = resp.text().await?;
+    serde_json::from_str(&body).map_err(|e| DerustError::decode(None, None, &body, &e))
+}
+
+impl From<reqwest::Error> for DerustError {
+    fn from(e: reqwest::Error) -> Self {
+        let mut message = e.to_string();
+        // the URL of a webhook contains its token, which
+        // would otherwise end up in the logs.
+        if let Some(url) = e.url() {
+            message = message.replace(&format!(" for url ({})", url), "");
+        }
+        DerustError::HttpError(message)
+    }
+}
+
+impl fmt::Display for DerustError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DerustError::HttpError(e) => write!(f, "HTTP request failed: {}", e),
+            DerustError::ApiError { status, error: Some(e) } => {
+                write!(f, "API returned {}: {} (code {})", status, e.message, e.code)
+            },
+            DerustError::ApiError { status, error: None } => write!(f, "API returned {}", status),
+            DerustError::MissingPermissions { missing } => write!(f,
+                "missing permissions: {:?}", missing.iter().collect::<Vec<_>>()
+            ),
+            DerustError::RoleHierarchy { target } => write!(f,
+                "{} is not below the highest role of the bot", target
+            ),
+            DerustError::InvalidToken => write!(f, "invalid token"),
+            DerustError::DecompressError => write!(f, "failed to decompress a gateway payload"),
+            DerustError::SessionStartLimit { remaining, reset_after } => write!(f,
+                "only {} session starts remaining, reset in {}ms", remaining, reset_after
+            ),
+            DerustError::DecodeError { op, t, message, snippet } => write!(f,
+                "failed to decode payload (op {:?}, t {:?}): {} at `{}`", op, t, message, snippet
+            ),
+            DerustError::GatewayClosed(code) => write!(f, "gateway closed the connection with {:?}", code),
+            DerustError::SessionInvalidated => write!(f, "the gateway invalidated the session"),
+            DerustError::Ratelimited { retry_after, global: true, .. } => {
+                write!(f, "globally ratelimited, retry after {}ms", retry_after)
+            },
+            DerustError::Ratelimited { retry_after, bucket, .. } => write!(f,
+                "ratelimited on bucket {:?}, retry after {}ms", bucket, retry_after
+            ),
+            DerustError::InvalidInput(e) => write!(f, "invalid input: {}", e),
+            DerustError::ClientShutDown => write!(f, "the client has shut down"),
+            DerustError::Timeout => write!(f, "timed out"),
+        }
+    }
 }
+
+impl std::error::Error for DerustError {}
@@ -1,47 +1,86 @@
 use super::super::message::Emoji;
-use super::super::user::User;
+use super::super::user::PartialUser;
 use serde::{Serialize, Deserialize};
+use serde_repr::{Serialize_repr, Deserialize_repr};
 use crate::types::{Snowflake, Timestamp};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct PresenceUpdate {
-    pub(crate) user: User,
-    pub(crate) roles: Vec<Snowflake>,
-    pub(crate) game: Activity,
-    pub(crate) guild_id: Snowflake,
-    pub(crate) status: String,
-    pub(crate) activities: Vec<Activity>,
-    pub(crate) client_status: ClientStatus,
-    pub(crate) premium_since: Option<Timestamp>,
-    pub(crate) nick: Option<String>
+    pub user: PartialUser,
+    /// Not included in the presences of a guild create.
+    #[serde(default)]
+    pub roles: Vec<Snowflake>,
+    pub game: Option<Activity>,
+    /// Not included in the presences of a guild create.
+    pub guild_id: Option<Snowflake>,
+    pub status: String,
+    pub activities: Vec<Activity>,
+    pub client_status: ClientStatus,
+    pub premium_since: Option<Timestamp>,
+    pub nick: Option<String>
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct Activity {
-    pub(crate) name: String,
-    pub(crate) r#type: ActivityType,
-    pub(crate) url: Option<String>,
-    pub(crate) created_at: i32,
-    pub(crate) timestamps: Option<ActivityTimestamp>,
-    pub(crate) application_id: Option<Snowflake>,
-    pub(crate) details: Option<String>,
-    pub(crate) state: Option<String>,
-    pub(crate) emoji: Option<Emoji>,
-    pub(crate) party: Option<ActivityParty>,
-    pub(crate) assets: Option<ActivityAssets>,
-    pub(crate) secrets: Option<ActivitySecrets>,
+    pub name: String,
+    pub r#type: ActivityType,
+    pub url: Option<String>,
+    pub created_at: u64, // unix timestamp in ms
+    pub timestamps: Option<ActivityTimestamp>,
+    pub application_id: Option<Snowflake>,
+    pub details: Option<String>,
+    pub state: Option<String>,
+    pub emoji: Option<Emoji>,
+    pub party: Option<ActivityParty>,
+    pub assets: Option<ActivityAssets>,
+    pub secrets: Option<ActivitySecrets>,
     #[serde(default)]
-    pub(crate) instance: bool,
-    pub(crate) flags: Option<ActivityFlags>
+    pub instance: bool,
+    pub flags: Option<u32>, // see ActivityFlags
+}
+
+impl Activity {
+    /// Construct a new [`Activity`] to be shown
+    /// in a [`StatusUpdate`], such as "Playing `name`".
+    pub fn new(name: String, r#type: ActivityType) -> Self {
+        let created_at = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        Activity {
+            name,
+            r#type,
+            url: None,
+            created_at,
+            timestamps: None,
+            application_id: None,
+            details: None,
+            state: None,
+            emoji: None,
+            party: None,
+            assets: None,
+            secrets: None,
+            instance: false,
+            flags: None,
+        }
+    }
+
+    /// Set the URL of the stream, only
+    /// used with [`ActivityType::Streaming`].
+    pub fn url(mut self, url: String) -> Self {
+        self.url = Some(url);
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct ActivityTimestamp {
-    pub(crate) start: Option<i32>, // unix timestamp
-    pub(crate) end: Option<i32>, // unix timestamp
+    pub start: Option<u64>, // unix timestamp in ms
+    pub end: Option<u64>, // unix timestamp in ms
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub enum ActivityType {
     Game = 0,
     Streaming = 1,
@@ -51,30 +90,30 @@ pub enum ActivityType {
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct ClientStatus {
-    pub(crate) desktop: Option<String>,
-    pub(crate) mobile: Option<String>,
-    pub(crate) web: Option<String>,
+    pub desktop: Option<String>,
+    pub mobile: Option<String>,
+    pub web: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct ActivityParty {
-    pub(crate) id: Option<Snowflake>,
-    pub(crate) size: Vec<i32>,
+    pub id: Option<Snowflake>,
+    pub size: Option<Vec<i32>>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct ActivityAssets {
-    pub(crate) large_image: Option<String>,
-    pub(crate) large_text: Option<String>,
-    pub(crate) small_image: Option<String>,
-    pub(crate) small_text: Option<String>,
+    pub large_image: Option<String>,
+    pub large_text: Option<String>,
+    pub small_image: Option<String>,
+    pub small_text: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct ActivitySecrets {
-    pub(crate) join: Option<String>,
-    pub(crate) spectate: Option<String>,
-    pub(crate) r#match: Option<String>,
+    pub join: Option<String>,
+    pub spectate: Option<String>,
+    pub r#match: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -95,13 +134,14 @@ pub enum ActivityFlags {
 pub struct StatusUpdate {
     /// This represents the amount of time since which the client
     /// is in an AFK state, or none.
-    since: Option<u64>, // unix timestamp in ms
-    /// This is the activity object/game that is being displayed.
-    game: Activity,
+    pub since: Option<u64>, // unix timestamp in ms
+    /// This is the activity object/game that is being displayed,
+    /// or none to clear it.
+    pub game: Option<Activity>,
     /// This is the current status of the user. Correct values:
     /// `online`, `dnd`, `idle`, `invisible`, `offline`
-    status: String,
-    afk: bool,
+    pub status: String,
+    pub afk: bool,
 }
 
 impl StatusUpdate {
@@ -109,7 +149,7 @@ impl StatusUpdate {
     pub fn new(activity: Activity, status: Status) -> Self {
         StatusUpdate {
             since: None,
-            game: activity,
+            game: Some(activity),
             status: status.to_string(),
             afk: false,
         }
@@ -128,14 +168,14 @@ pub enum Status {
     Offline,
 }
 
-impl ToString for Status {
-    fn to_string(&self) -> String {
-        match self {
-            Status::Online => String::from("online"),
-            Status::DND => String::from("dnd"),
-            Status::Idle => String::from("idle"),
-            Status::Invisible => String::from("invisible"),
-            Status::Offline => String::from("offline"),
-        }
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Online => "online",
+            Status::DND => "dnd",
+            Status::Idle => "idle",
+            Status::Invisible => "invisible",
+            Status::Offline => "offline",
+        })
     }
 }
\ No newline at end of file
@@ -1,23 +1,26 @@
 use serde::Serialize;
 use crate::types::gateway::activity::{StatusUpdate};
+use crate::types::error::DerustError;
 use enumflags2::BitFlags;
 
-#[derive(Serialize, Deserialize, Eq, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
 pub struct IdentifyObject {
     pub token: String,
     pub properties: IdentifyProperties,
     pub compress: bool,
     pub large_threshold: Option<u8>,
-    pub shard: Option<Vec<u8>>,
+    pub shard: Option<[u16; 2]>,
     pub presence: Option<StatusUpdate>,
     #[serde(default)]
     pub guild_subscriptions: bool,
     pub intents: Option<u32>,
     #[serde(skip)]
     pub auto_reidentify: bool,
+    #[serde(skip)]
+    pub transport_compression: bool,
 }
 
-#[derive(Serialize, Deserialize, Eq, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
 pub struct IdentifyProperties {
     #[serde(rename = "$os")]
     pub os: String,
@@ -47,6 +50,7 @@ impl IdentifyObject {
             guild_subscriptions: false,
             intents: None,
             auto_reidentify: false,
+            transport_compression: false,
         }
     }
     /// Whether or not to use zlib compression (achieved with
@@ -62,6 +66,22 @@ impl IdentifyObject {
         self
     }
 
+    /// Whether or not to use `zlib-stream` transport compression,
+    /// where the whole connection is compressed as a single zlib stream,
+    /// which saves a lot more bandwidth than the per-payload compression.
+    ///
+    /// Enabling it disables [`compress`](#method.compress), since
+    /// both of them cannot be used at the same time.
+    ///
+    /// Default: false
+    pub fn transport_compression(&mut self, choice: bool) -> &mut Self {
+        self.transport_compression = choice;
+        if choice {
+            self.compress = false;
+        }
+        self
+    }
+
     /// The gateway intents that will be used throughout this session.
     /// To construct them, use the [`Intents`](#Intents) enum as follows:
     /// ```rust
@@ -72,8 +92,8 @@ impl IdentifyObject {
     /// async fn main() {
     ///     let mut intents = BitFlags::from_flag(Intents::GuildMessages);
     ///     intents.insert(Intents::DirectMessages);
-    ///     let mut identify = IdentifyObject::new(String::from("token here"))
-    ///         .intents(intents.bits());
+    ///     let mut identify = IdentifyObject::new(String::from("token here"));
+    ///     identify.intents(intents.bits().into());
     /// }
     /// ```
     /// Or it is also possible to do:
@@ -83,9 +103,9 @@ impl IdentifyObject {
     ///
     /// #[tokio::main]
     /// async fn main() {
-    ///     let mut intents = BitFlags::from_bits(0b010010); // GuildMembers and GuildIntegrations
-    ///     let mut identify = IdentifyObject::new(String::from("token here"))
-    ///         .intents(intents.bits().try_into().unwrap()); // you should probably never unwrap this, instead use something like the ? shorthand
+    ///     let intents = BitFlags::<Intents>::from_bits(0b010010).unwrap(); // GuildMembers and GuildIntegrations, you should probably never unwrap this, instead use something like the ? shorthand
+    ///     let mut identify = IdentifyObject::new(String::from("token here"));
+    ///     identify.intents(intents.bits().into());
     /// }
     /// ```
     ///
@@ -95,6 +115,25 @@ impl IdentifyObject {
         self
     }
 
+    /// The shard this session will be receiving the events of,
+    /// as `shard_id` out of `shard_count` shards.
+    ///
+    /// You shouldn't need to set it yourself, the [`ShardManager`]
+    /// does it for each of the shards it starts.
+    ///
+    /// [`ShardManager`]: super::shard::ShardManager
+    ///
+    /// Default: none
+    pub fn shard(&mut self, shard_id: u16, shard_count: u16) -> Result<&mut Self, DerustError> {
+        if shard_id >= shard_count {
+            return Err(DerustError::InvalidInput(
+                format!("shard {} is out of range for {} shards", shard_id, shard_count)
+            ));
+        }
+        self.shard = Some([shard_id, shard_count]);
+        Ok(self)
+    }
+
     /// The presence that will be sent on the gateway IDENTIFY OPCode,
     /// which will be displayed until modified manually later on in code.
     ///
@@ -110,15 +149,17 @@ impl IdentifyObject {
     /// stop sending offline user objects in guilds with a greater
     /// or equal value to the one specified.
     ///
-    /// Must be between 50 and 250, otherwise the default of 50
-    /// will be used.
-    pub fn large_threshold(&mut self, threshold: u8) -> &mut Self {
-        if threshold >= 50 && threshold <= 250 {
-            self.large_threshold = Some(threshold);
-            self
-        } else {
-            self
+    /// Must be between 50 and 250.
+    ///
+    /// Default: 50
+    pub fn large_threshold(&mut self, threshold: u8) -> Result<&mut Self, DerustError> {
+        if !(50..=250).contains(&threshold) {
+            return Err(DerustError::InvalidInput(
+                format!("large_threshold must be between 50 and 250, got {}", threshold)
+            ));
         }
+        self.large_threshold = Some(threshold);
+        Ok(self)
     }
 
     /// Whether or not to receive [`GuildMember`](../../guild/index.html) presence and typing
@@ -132,6 +173,10 @@ impl IdentifyObject {
     /// Whether or not to automatically re-identify once the session has been attempted to be
     /// resumed, but it failed and we were requested to re-identify completely.
     ///
+    /// If disabled, the client will shut down instead, sending a
+    /// `DerustError::SessionInvalidated` before closing the
+    /// event receiver returned by `Client::login`.
+    ///
     /// Default: `false`
     pub fn auto_reidentify(&mut self, choice: bool) -> &mut Self {
         self.auto_reidentify = choice;
@@ -149,7 +194,7 @@ impl IdentifyObject {
 ///
 /// Pay careful attention to `GuildPresences` and
 /// `GuildMembers` because they are **privileged** intents.
-#[derive(Serialize, BitFlags, Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize, BitFlags, Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u16)]
 pub enum Intents {
     Guilds = 1 << 0,
@@ -1,33 +1,56 @@
-use std::sync::Arc;
-use crate::types::guild::GuildMember;
+use std::sync::{Arc, Mutex};
+use crate::types::guild::{Guild, GuildMember, PartialGuild};
 use crate::types::user::User;
 use crate::types::voice::VoiceState;
 use crate::types::channel::Channel;
 use std::collections::HashMap;
+use std::fmt;
 use crate::types::{CachedTypes, Snowflake};
 use super::payloads::{DiscordEvent, GuildType};
+use super::activity::PresenceUpdate;
+
+/// A [`Cache`] shared between the client and the user.
+pub type SharedCache<C> = Arc<Mutex<dyn Cache<C> + Send>>;
+
+/// The members of a single guild, by user ID, since
+/// a user has a member in every guild they are in.
+pub type GuildMembers = HashMap<Snowflake, GuildMember>;
+
+/// The voice states of a single guild, by user ID.
+pub type GuildVoiceStates = HashMap<Snowflake, VoiceState>;
+
+impl<V: CachedTypes> CachedTypes for HashMap<Snowflake, V> {}
 
 #[derive(Clone)]
 pub struct Caches {
-    guild_cache: Option<Arc<dyn Cache<GuildType>>>,
-    user_cache: Option<Arc<dyn Cache<User>>>,
-    member_cache: Option<Arc<dyn Cache<GuildMember>>>,
-    voice_state_cache: Option<Arc<dyn Cache<VoiceState>>>,
-    channel_cache: Option<Arc<dyn Cache<Channel>>>,
+    guild_cache: Option<SharedCache<GuildType>>,
+    user_cache: Option<SharedCache<User>>,
+    member_cache: Option<SharedCache<GuildMembers>>,
+    voice_state_cache: Option<SharedCache<GuildVoiceStates>>,
+    channel_cache: Option<SharedCache<Channel>>,
 }
 
-unsafe impl Sync for Caches {}
-
-unsafe impl Send for Caches {}
+impl fmt::Debug for Caches {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the caches themselves can be anything.
+        f.debug_struct("Caches")
+            .field("guild_cache", &self.guild_cache.is_some())
+            .field("user_cache", &self.user_cache.is_some())
+            .field("member_cache", &self.member_cache.is_some())
+            .field("voice_state_cache", &self.voice_state_cache.is_some())
+            .field("channel_cache", &self.channel_cache.is_some())
+            .finish()
+    }
+}
 
 impl Caches {
     pub async fn initialize() -> Self {
         Self {
-            guild_cache: Some(Arc::new(HashMap::new())),
-            user_cache: Some(Arc::new(HashMap::new())),
-            member_cache: Some(Arc::new(HashMap::new())),
-            voice_state_cache: Some(Arc::new(HashMap::new())),
-            channel_cache: Some(Arc::new(HashMap::new())),
+            guild_cache: Some(Arc::new(Mutex::new(HashMap::new()))),
+            user_cache: Some(Arc::new(Mutex::new(HashMap::new()))),
+            member_cache: Some(Arc::new(Mutex::new(HashMap::new()))),
+            voice_state_cache: Some(Arc::new(Mutex::new(HashMap::new()))),
+            channel_cache: Some(Arc::new(Mutex::new(HashMap::new()))),
         }
     }
     /// Supply your own object that implements [`Cache`] to the struct and add it,
@@ -36,7 +59,7 @@ impl Caches {
     /// Default: `HashMap<Snowflake, Guild>` with no limits.
     pub async fn guild_cache(
         &mut self,
-        cache: Option<Arc<dyn Cache<GuildType>>>
+        cache: Option<SharedCache<GuildType>>
     ) -> &mut Self
     {
         self.guild_cache = cache;
@@ -48,29 +71,30 @@ impl Caches {
     /// Default: `HashMap<Snowflake, User>` with no limits.
     pub async fn user_cache(
         &mut self,
-        cache: Option<Arc<dyn Cache<User>>>
+        cache: Option<SharedCache<User>>
     ) -> &mut Self {
         self.user_cache = cache;
         self
     }
 
-    /// Same as [`guild_cache`](method.guild_cache).
+    /// Same as [`guild_cache`](method.guild_cache), the members
+    /// of every guild being cached together.
     ///
-    /// Default: `HashMap<Snowflake, GuildMember>` with no limits.
+    /// Default: `HashMap<Snowflake, GuildMembers>` with no limits.
     pub async fn member_cache(
         &mut self,
-        cache: Option<Arc<dyn Cache<GuildMember>>>
+        cache: Option<SharedCache<GuildMembers>>
     ) -> &mut Self {
         self.member_cache = cache;
         self
     }
 
-    /// Same as [`guild_cache`](method.guild_cache).
+    /// Same as [`member_cache`](method.member_cache).
     ///
-    /// Default: `HashMap<Snowflake, VoiceState>` with no limits.
+    /// Default: `HashMap<Snowflake, GuildVoiceStates>` with no limits.
     pub async fn voice_state_cache(
         &mut self,
-        cache: Option<Arc<dyn Cache<VoiceState>>>
+        cache: Option<SharedCache<GuildVoiceStates>>
     ) -> &mut Self {
         self.voice_state_cache = cache;
         self
@@ -81,11 +105,346 @@ impl Caches {
     /// Default: `HashMap<Snowflake, Channel>` with no limits.
     pub async fn channel_cache(
         &mut self,
-        cache: Option<Arc<dyn Cache<Channel>>>
+        cache: Option<SharedCache<Channel>>
     ) -> &mut Self {
         self.channel_cache = cache;
         self
     }
+
+    /// The cache of the guilds, if they are cached.
+    pub fn guilds(&self) -> Option<&SharedCache<GuildType>> {
+        self.guild_cache.as_ref()
+    }
+
+    /// The cache of the users, if they are cached.
+    pub fn users(&self) -> Option<&SharedCache<User>> {
+        self.user_cache.as_ref()
+    }
+
+    /// The cache of the members, keyed by the ID of their guild,
+    /// each guild having its own by user ID, if they are cached.
+    pub fn members(&self) -> Option<&SharedCache<GuildMembers>> {
+        self.member_cache.as_ref()
+    }
+
+    /// The cache of the voice states, keyed by the ID of their
+    /// guild like the members, if they are cached.
+    pub fn voice_states(&self) -> Option<&SharedCache<GuildVoiceStates>> {
+        self.voice_state_cache.as_ref()
+    }
+
+    /// The cache of the channels, if they are cached.
+    pub fn channels(&self) -> Option<&SharedCache<Channel>> {
+        self.channel_cache.as_ref()
+    }
+
+    /// Add `guilds` to the guild cache, replacing
+    /// the ones that were already cached.
+    pub(crate) fn push_guilds(&self, guilds: impl Iterator<Item = GuildType>) {
+        let cache = match &self.guild_cache {
+            Some(cache) => cache,
+            None => return,
+        };
+        let mut cache = cache.lock().expect("guild cache poisoned");
+        for guild in guilds {
+            let id = match &guild {
+                GuildType::Full(g) => g.id,
+                GuildType::Partial(g) => g.id,
+            };
+            cache.push(id, guild);
+        }
+    }
+
+    /// Add `members` of the guild `guild_id` to the member
+    /// cache, replacing the ones that were already cached.
+    pub(crate) fn push_members(&self, guild_id: Snowflake, members: &[GuildMember]) {
+        let cache = match &self.member_cache {
+            Some(cache) => cache,
+            None => return,
+        };
+        let mut cache = cache.lock().expect("member cache poisoned");
+        let mut cached = cache.remove(guild_id).unwrap_or_default();
+        for member in members {
+            if let Some(user) = &member.user {
+                cached.insert(user.id, member.clone());
+            }
+        }
+        cache.push(guild_id, cached);
+    }
+
+    /// Update the caches from a dispatched `event`, before it is
+    /// given to the user.
+    ///
+    /// The events which only contain some of the fields of an object,
+    /// like [`GuildUpdate`] or [`PresenceUpdate`], are merged into the
+    /// cached object, and the roles, channels, emojis, presences and
+    /// voice states of the cached guilds are kept up to date along
+    /// with their own caches. The members are only kept in the member
+    /// cache, the cached guilds having none.
+    ///
+    /// [`GuildUpdate`]: DiscordEvent::GuildUpdate
+    /// [`PresenceUpdate`]: DiscordEvent::PresenceUpdate
+    pub(crate) fn update(&self, event: &DiscordEvent) {
+        match event {
+            DiscordEvent::Ready(r) => {
+                self.push_users(std::iter::once(&r.user));
+                self.push_guilds(r.guilds.iter().cloned().map(GuildType::Partial));
+            },
+            DiscordEvent::GuildCreate(GuildType::Full(guild)) => self.create_guild(guild),
+            DiscordEvent::GuildCreate(guild) => self.push_guilds(std::iter::once(guild.clone())),
+            DiscordEvent::GuildUpdate(update) => {
+                with_cache(&self.guild_cache, |cache| {
+                    let guild = match cache.remove(update.id) {
+                        Some(GuildType::Full(guild)) => merge_guild(guild, update.clone()),
+                        _ => update.clone(),
+                    };
+                    cache.push(update.id, GuildType::Full(guild));
+                });
+            },
+            DiscordEvent::GuildDelete(deleted) => self.delete_guild(deleted.id, deleted.unavailable),
+            DiscordEvent::ChannelCreate(channel) | DiscordEvent::ChannelUpdate(channel) => {
+                with_cache(&self.channel_cache, |cache| cache.push(channel.id, channel.clone()));
+                if let Some(guild_id) = channel.guild_id {
+                    self.modify_guild(guild_id, |guild| {
+                        let channels = guild.channels.get_or_insert_with(Vec::new);
+                        upsert(channels, channel.clone(), |c| c.id == channel.id);
+                    });
+                }
+            },
+            DiscordEvent::ChannelDelete(channel) => {
+                with_cache(&self.channel_cache, |cache| cache.remove(channel.id));
+                if let Some(guild_id) = channel.guild_id {
+                    self.modify_guild(guild_id, |guild| {
+                        if let Some(channels) = &mut guild.channels {
+                            channels.retain(|c| c.id != channel.id);
+                        }
+                    });
+                }
+            },
+            DiscordEvent::GuildMemberAdd(add) => {
+                self.add_members(add.guild_id, std::slice::from_ref(&add.member));
+                self.modify_guild(add.guild_id, |guild| {
+                    guild.member_count = guild.member_count.map(|count| count + 1);
+                });
+            },
+            DiscordEvent::GuildMemberUpdate(update) => {
+                let merge = |member: &mut GuildMember| {
+                    member.user = Some(update.user.clone());
+                    member.roles = update.roles.clone();
+                    member.nick = update.nick.clone();
+                    member.premium_since = update.premium_since.map(|t| t.0.to_rfc3339());
+                };
+                self.push_users(std::iter::once(&update.user));
+                modify(&self.member_cache, update.guild_id, |members| {
+                    if let Some(member) = members.get_mut(&update.user.id) {
+                        merge(member);
+                    }
+                });
+            },
+            DiscordEvent::GuildMemberRemove(remove) => {
+                modify(&self.member_cache, remove.guild_id, |members| {
+                    members.remove(&remove.user.id);
+                });
+                self.modify_guild(remove.guild_id, |guild| {
+                    guild.member_count = guild.member_count.map(|count| count - 1);
+                });
+            },
+            DiscordEvent::GuildMembersChunk(chunk) => self.add_members(chunk.guild_id, &chunk.members),
+            DiscordEvent::GuildRoleCreate(r) | DiscordEvent::GuildRoleUpdate(r) => {
+                self.modify_guild(r.guild_id, |guild| {
+                    upsert(&mut guild.roles, r.role.clone(), |role| role.id == r.role.id);
+                });
+            },
+            DiscordEvent::GuildRoleDelete(r) => {
+                self.modify_guild(r.guild_id, |guild| {
+                    guild.roles.retain(|role| role.id != r.role_id);
+                });
+                modify(&self.member_cache, r.guild_id, |members| {
+                    for member in members.values_mut() {
+                        member.roles.retain(|id| *id != r.role_id);
+                    }
+                });
+            },
+            DiscordEvent::GuildEmojisUpdate(update) => {
+                self.modify_guild(update.guild_id, |guild| guild.emojis = update.emojis.clone());
+            },
+            DiscordEvent::PresenceUpdate(presence) => self.update_presence(presence),
+            DiscordEvent::VoiceStateUpdate(state) => {
+                if let Some(guild_id) = state.guild_id {
+                    with_cache(&self.voice_state_cache, |cache| {
+                        let mut states = cache.remove(guild_id).unwrap_or_default();
+                        // a voice state without a channel is a user leaving it.
+                        match state.channel_id {
+                            Some(_) => states.insert(state.user_id, state.clone()),
+                            None => states.remove(&state.user_id),
+                        };
+                        cache.push(guild_id, states);
+                    });
+                    self.modify_guild(guild_id, |guild| {
+                        let states = guild.voice_states.get_or_insert_with(Vec::new);
+                        states.retain(|s| s.user_id != state.user_id);
+                        if state.channel_id.is_some() {
+                            states.push(state.clone());
+                        }
+                    });
+                }
+            },
+            DiscordEvent::UserUpdate(user) => self.push_users(std::iter::once(user)),
+            _ => {},
+        }
+    }
+
+    fn push_users<'a>(&self, users: impl Iterator<Item = &'a User>) {
+        with_cache(&self.user_cache, |cache| {
+            for user in users {
+                cache.push(user.id, user.clone());
+            }
+        });
+    }
+
+    /// Cache a guild along with everything it contains,
+    /// its members going to the member cache only.
+    fn create_guild(&self, guild: &Guild) {
+        let members = guild.members.as_deref().unwrap_or(&[]);
+        self.push_users(members.iter().filter_map(|m| m.user.as_ref()));
+        self.push_members(guild.id, members);
+        with_cache(&self.channel_cache, |cache| {
+            // the channels of a guild create don't have it set.
+            for channel in guild.channels.iter().flatten() {
+                let mut channel = channel.clone();
+                channel.guild_id = Some(guild.id);
+                cache.push(channel.id, channel);
+            }
+        });
+        with_cache(&self.voice_state_cache, |cache| {
+            let states = guild.voice_states.iter().flatten().map(|state| {
+                let mut state = state.clone();
+                state.guild_id = Some(guild.id);
+                (state.user_id, state)
+            });
+            cache.push(guild.id, states.collect());
+        });
+        with_cache(&self.guild_cache, |cache| {
+            let guild = Guild { members: None, ..guild.clone() };
+            cache.push(guild.id, GuildType::Full(guild));
+        });
+    }
+
+    /// Remove a guild the bot left, along with its channels, members
+    /// and voice states, or mark it as unavailable if it is only during
+    /// an outage.
+    fn delete_guild(&self, guild_id: Snowflake, unavailable: bool) {
+        let removed = with_cache(&self.guild_cache, |cache| {
+            let removed = cache.remove(guild_id);
+            if unavailable {
+                cache.push(guild_id, GuildType::Partial(PartialGuild { id: guild_id, unavailable }));
+            }
+            removed
+        }).flatten();
+        if unavailable {
+            return;
+        }
+        with_cache(&self.member_cache, |cache| cache.remove(guild_id));
+        with_cache(&self.voice_state_cache, |cache| cache.remove(guild_id));
+        if let Some(GuildType::Full(guild)) = removed {
+            with_cache(&self.channel_cache, |cache| {
+                for channel in guild.channels.iter().flatten() {
+                    cache.remove(channel.id);
+                }
+            });
+        }
+    }
+
+    /// Add or replace `members` of a guild, along with their users.
+    fn add_members(&self, guild_id: Snowflake, members: &[GuildMember]) {
+        self.push_users(members.iter().filter_map(|m| m.user.as_ref()));
+        self.push_members(guild_id, members);
+    }
+
+    /// Merge a presence update into the cached user and member,
+    /// and the presences of the cached guild.
+    fn update_presence(&self, presence: &PresenceUpdate) {
+        let user = &presence.user;
+        modify(&self.user_cache, user.id, |cached| {
+            if let Some(username) = &user.username {
+                cached.username = username.clone();
+            }
+            if let Some(discriminator) = &user.discriminator {
+                cached.discriminator = discriminator.clone();
+            }
+            if user.avatar.is_some() {
+                cached.avatar = user.avatar.clone();
+            }
+        });
+        let guild_id = match presence.guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+        modify(&self.member_cache, guild_id, |members| {
+            if let Some(member) = members.get_mut(&user.id) {
+                member.roles = presence.roles.clone();
+                member.nick = presence.nick.clone();
+            }
+        });
+        self.modify_guild(guild_id, |guild| {
+            let presences = guild.presences.get_or_insert_with(Vec::new);
+            upsert(presences, presence.clone(), |p| p.user.id == user.id);
+        });
+    }
+
+    /// Modify the guild `guild_id` if it is cached and available.
+    fn modify_guild(&self, guild_id: Snowflake, f: impl FnOnce(&mut Guild)) {
+        modify(&self.guild_cache, guild_id, |guild| {
+            if let GuildType::Full(guild) = guild {
+                f(guild);
+            }
+        });
+    }
+}
+
+/// Run `f` on `cache`, if it is enabled.
+fn with_cache<C: CachedTypes, T>(
+    cache: &Option<SharedCache<C>>,
+    f: impl FnOnce(&mut (dyn Cache<C> + Send)) -> T,
+) -> Option<T> {
+    let cache = cache.as_ref()?;
+    let mut cache = cache.lock().expect("cache poisoned");
+    Some(f(&mut *cache))
+}
+
+/// Modify the object cached as `k` in `cache`, if there is one.
+fn modify<C: CachedTypes>(cache: &Option<SharedCache<C>>, k: Snowflake, f: impl FnOnce(&mut C)) {
+    with_cache(cache, |cache| {
+        // `Cache` only gives out shared references, so
+        // the object is taken out and put back in.
+        if let Some(mut v) = cache.remove(k) {
+            f(&mut v);
+            cache.push(k, v);
+        }
+    });
+}
+
+/// Replace the first element of `items` matching `is_same`
+/// with `item`, or add it if there is none.
+fn upsert<T>(items: &mut Vec<T>, item: T, is_same: impl Fn(&T) -> bool) {
+    match items.iter_mut().find(|i| is_same(i)) {
+        Some(existing) => *existing = item,
+        None => items.push(item),
+    }
+}
+
+/// Merge a guild update into the cached `guild`, keeping the fields
+/// which are only sent when the guild is created.
+fn merge_guild(guild: Guild, update: Guild) -> Guild {
+    Guild {
+        joined_at: guild.joined_at,
+        large: guild.large,
+        member_count: guild.member_count,
+        voice_states: guild.voice_states,
+        channels: guild.channels,
+        presences: guild.presences,
+        ..update
+    }
 }
 
 /// The base trait for your cache. By default, it is
@@ -94,8 +453,7 @@ impl Caches {
 /// using a database like Redis to cache.
 ///
 /// An example implementation would be something like:
-/// ```
-/// use derust::types::gateway::client::Cache;
+/// ```ignore
 /// use std::collections::HashMap;
 /// use derust::types::{Snowflake, CachedTypes};
 /// use derust::types::gateway::cache::Cache;
@@ -132,6 +490,10 @@ pub trait Cache<C: CachedTypes> {
     fn remove(&mut self, k: Snowflake) -> Option<C>;
     fn clear(&mut self);
     fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 impl<V> Cache<V> for HashMap<Snowflake, V>
@@ -157,42 +519,3 @@ impl<V> Cache<V> for HashMap<Snowflake, V>
         self.len()
     }
 }
-
-pub struct CacheRequest {
-    pub action: CacheAction,
-    pub cache_type: CacheType,
-    pub tx: tokio::sync::oneshot::Sender<CacheResponse>,
-}
-
-impl CacheRequest {
-    pub fn new (
-        action: CacheAction, 
-        cache_type: CacheType, 
-        tx: tokio::sync::oneshot::Sender<CacheResponse>
-    ) -> Self {
-        Self {action, cache_type, tx}
-    }
-}
-
-pub enum CacheAction {
-    Get(Snowflake),
-    Push((Snowflake, DiscordEvent)),
-    Remove(Snowflake),
-    Clear,
-    Len(usize),
-}
-
-pub enum CacheResponse {
-    DiscordObject(DiscordEvent),
-    DiscordObjectRef(&'static DiscordEvent),
-    Size(usize),
-    Ok,
-}
-
-pub enum CacheType {
-    Guild,
-    User,
-    Member,
-    VoiceState,
-    Channel,
-}
\ No newline at end of file
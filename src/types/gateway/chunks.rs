@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use crate::types::Snowflake;
+use crate::types::guild::GuildMember;
+use super::activity::PresenceUpdate;
+use super::events::GuildMembersChunk;
+
+/// Every member returned for a request made with
+/// [`ShardHandle::collect_guild_members`], gathered
+/// from all the chunks sent in response to it.
+///
+/// [`ShardHandle::collect_guild_members`]: super::handle::ShardHandle::collect_guild_members
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemberChunks {
+    pub guild_id: Option<Snowflake>,
+    pub members: Vec<GuildMember>,
+    /// Only filled when presences were requested.
+    pub presences: Vec<PresenceUpdate>,
+    /// IDs that were requested but weren't found.
+    pub not_found: Vec<Snowflake>,
+}
+
+/// The chunks received so far for a request.
+#[derive(Debug)]
+struct Collector {
+    chunks: MemberChunks,
+    /// Which chunks were received, sized on the first one.
+    received: Vec<bool>,
+    tx: oneshot::Sender<MemberChunks>,
+}
+
+/// The requests waiting for their chunks, by nonce, shared
+/// between a client and all of its [`ShardHandle`]s.
+///
+/// [`ShardHandle`]: super::handle::ShardHandle
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PendingChunks {
+    collectors: Arc<Mutex<HashMap<String, Collector>>>,
+}
+
+impl PendingChunks {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start collecting the chunks sent with `nonce`, the
+    /// receiver resolving once all of them have arrived.
+    pub(crate) fn register(&self, nonce: String) -> oneshot::Receiver<MemberChunks> {
+        let (tx, rx) = oneshot::channel();
+        self.collectors.lock().expect("chunk collectors poisoned").insert(nonce, Collector {
+            chunks: MemberChunks::default(),
+            received: Vec::new(),
+            tx,
+        });
+        rx
+    }
+
+    /// Stop collecting the chunks sent with `nonce`,
+    /// such as after the request timed out.
+    pub(crate) fn cancel(&self, nonce: &str) {
+        self.collectors.lock().expect("chunk collectors poisoned").remove(nonce);
+    }
+
+    /// Stop collecting the chunks of every request, which
+    /// makes their receivers fail since their senders get dropped.
+    pub(crate) fn cancel_all(&self) {
+        self.collectors.lock().expect("chunk collectors poisoned").clear();
+    }
+
+    /// Add a chunk to the request it answers, if that request is
+    /// being collected, completing it if it was the last chunk.
+    pub(crate) fn feed(&self, chunk: &GuildMembersChunk) {
+        let nonce = match &chunk.nonce {
+            Some(nonce) => nonce,
+            None => return,
+        };
+        let mut collectors = self.collectors.lock().expect("chunk collectors poisoned");
+        let collector = match collectors.get_mut(nonce) {
+            Some(collector) => collector,
+            None => return,
+        };
+        if collector.received.is_empty() {
+            collector.received = vec![false; chunk.chunk_count.max(1) as usize];
+        }
+        match collector.received.get_mut(chunk.chunk_index as usize) {
+            // ignore duplicated or out of range chunks.
+            Some(received) if !*received => *received = true,
+            _ => return,
+        }
+        let chunks = &mut collector.chunks;
+        chunks.guild_id = Some(chunk.guild_id);
+        chunks.members.extend(chunk.members.iter().cloned());
+        if let Some(presences) = &chunk.presences {
+            chunks.presences.extend(presences.iter().cloned());
+        }
+        if let Some(not_found) = &chunk.not_found {
+            chunks.not_found.extend(not_found.iter().cloned());
+        }
+        if collector.received.iter().all(|r| *r) {
+            if let Some(collector) = collectors.remove(nonce) {
+                // the request may have timed out in the meantime.
+                let _ = collector.tx.send(collector.chunks);
+            }
+        }
+    }
+}
@@ -1,29 +1,70 @@
 use super::builder::IdentifyObject;
-use super::cache::{CacheRequest, Caches, CacheType, CacheAction};
-use tokio::sync::{mpsc, mpsc::Receiver, oneshot, Barrier};
-use tokio::runtime::Builder;
-use tracing::{trace};
-use tokio_tungstenite::{tungstenite::Message, connect_async};
-use crate::types::gateway::payloads::{GatewayPayload, ReadyObject};
-use crate::types::gateway::opcodes::OPCode;
+#[cfg(feature = "cache")]
+use super::cache::Caches;
+use tokio::sync::{mpsc, mpsc::{Receiver, Sender}};
+use tracing::{debug, error, trace, warn};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, frame::coding::CloseCode as TungsteniteCloseCode};
+use crate::types::gateway::payloads::{ReadyObject, ResumeObject, SendPayload};
+use crate::types::gateway::opcodes::{CloseCode, OPCode};
+use crate::types::error::DerustError;
+use crate::http::{Http, Request, routing::Route};
+use reqwest::Method;
 use tokio::time::{delay_for, Duration};
-use miniz_oxide::inflate::decompress_to_vec;
-use futures_util::{SinkExt, stream::StreamExt};
+use super::compression::{Decompressor, ZlibStream};
+use super::handle::ShardHandle;
+use super::chunks::PendingChunks;
+use futures_util::{future, Sink, SinkExt, stream::StreamExt};
+use serde::Serialize;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
-use super::payloads::{GatewayMessages, DiscordEvent, GatewayPayloadObjects, GuildType};
+use super::identify::{IdentifyQueue, LocalIdentifyQueue};
+use super::ratelimit::SendRatelimiter;
+use super::payloads::{GatewayMessages, GatewayPayload, DiscordEvent};
 
 pub struct Client {
     url: String,
     heartbeat_interval: Option<u64>,
+    heartbeat_acked: bool,
     session_id: Option<String>,
     seq: Option<u64>,
-    identify_object: &'static mut IdentifyObject,
+    identify_object: IdentifyObject,
+    identify_queue: Arc<dyn IdentifyQueue>,
+    ratelimiter: SendRatelimiter,
+    /// Serialized commands waiting to be sent, which are kept
+    /// across reconnects until they can be sent.
+    command_tx: Sender<String>,
+    command_rx: Receiver<String>,
+    /// A command which failed to be sent, sent
+    /// again before the ones still queued.
+    unsent_command: Option<String>,
+    /// Whether the current connection has identified or resumed,
+    /// before which commands can't be sent.
+    identified: bool,
+    pending_chunks: PendingChunks,
     ready_object: Option<ReadyObject>,
-    reqwest_client: reqwest::Client,
+    http: Http,
     #[cfg(feature = "cache")]
     cache: Caches,
 }
 
+/// Version of the gateway we connect to, matching
+/// the version of the API in `API_URL`.
+const GATEWAY_VERSION: u8 = 6;
+
+/// How many commands can be queued before
+/// sending more of them starts waiting.
+const COMMAND_QUEUE_SIZE: usize = 120;
+
+/// Seconds to wait before reconnecting to the gateway
+/// after a session has died.
+const RECONNECT_DELAY: u64 = 5;
+
+/// Our turn in the identify queue, which is waited for
+/// alongside the heartbeats of the connection.
+type IdentifyTurn = Pin<Box<dyn Future<Output = ()> + Send>>;
+
 #[derive(Debug, Clone, Deserialize)]
 struct GatewayUrl {
     pub url: String,
@@ -42,22 +83,68 @@ impl Client {
     /// it.
     ///
     /// [`Cache`]: types::gateway::cache::Cache
-    pub async fn new(mut io: IdentifyObject, caches: Caches) -> Self {        let mut headers = reqwest::header::HeaderMap::new();
-            headers.insert(reqwest::header::AUTHORIZATION, 
-            reqwest::header::HeaderValue::from_str(io.token.as_str()).unwrap()).unwrap();
-        let reqwest_client = reqwest::Client::builder()
-            .user_agent("DiscordBot (https://github.com/keksbg/derust, 0.1)")
-            .default_headers(headers).build().unwrap();
-         Self {
-            url: reqwest::get(&(crate::API_URL.to_owned() + "/gateway")).await.unwrap().json::<GatewayUrl>().await.unwrap().url,
+    pub async fn new(
+        io: IdentifyObject,
+        #[cfg(feature = "cache")] caches: Caches,
+    ) -> Result<Self, DerustError> {
+        #[cfg(feature = "cache")]
+        let mut client = Self::with_url(io, caches, String::new())?;
+        #[cfg(not(feature = "cache"))]
+        let mut client = Self::with_url(io, String::new())?;
+        let route = Route::new(Method::GET, String::from("/gateway"));
+        client.url = client.http.request::<GatewayUrl>(Request::new(route)).await?.url;
+        Ok(client)
+    }
+
+    /// Same as [`new`](#method.new), but connecting to the
+    /// specified gateway URL instead of requesting it, such as
+    /// the one obtained by the [`ShardManager`].
+    ///
+    /// [`ShardManager`]: types::gateway::shard::ShardManager
+    pub fn with_url(
+        io: IdentifyObject,
+        #[cfg(feature = "cache")] caches: Caches,
+        url: String,
+    ) -> Result<Self, DerustError> {
+        let http = Http::new(&io.token)?;
+        #[cfg(feature = "cache")]
+        return Ok(Self::with_http(io, caches, url, http));
+        #[cfg(not(feature = "cache"))]
+        Ok(Self::with_http(io, url, http))
+    }
+
+    /// Same as [`with_url`](#method.with_url), but using `http` for the
+    /// REST API, which has to be authenticated with the same token.
+    ///
+    /// Clients sharing an `Http` also share its ratelimits, as
+    /// the shards started by the [`ShardManager`] do.
+    ///
+    /// [`ShardManager`]: types::gateway::shard::ShardManager
+    pub fn with_http(
+        io: IdentifyObject,
+        #[cfg(feature = "cache")] caches: Caches,
+        url: String,
+        http: Http,
+    ) -> Self {
+        let (command_tx, command_rx) = mpsc::channel(COMMAND_QUEUE_SIZE);
+        Self {
+            url,
             heartbeat_interval: None,
+            heartbeat_acked: true,
             session_id: None,
             seq: None,
-            identify_object: &io,
+            identify_object: io,
+            identify_queue: Arc::new(LocalIdentifyQueue::new(1)),
+            ratelimiter: SendRatelimiter::new(),
+            command_tx,
+            command_rx,
+            unsent_command: None,
+            identified: false,
+            pending_chunks: PendingChunks::new(),
             ready_object: None,
             #[cfg(feature = "cache")]
             cache: caches,
-            reqwest_client
+            http,
         }
     }
 
@@ -71,110 +158,365 @@ impl Client {
         self
     }
 
+    /// The REST API client authenticated with the token of this
+    /// client, to be cloned before logging in to keep using it.
+    pub fn http(&self) -> &Http {
+        &self.http
+    }
+
+    /// Set the queue which decides when this client is allowed to
+    /// identify, which has to be shared with every other client
+    /// using the same token so that they don't get ratelimited.
+    ///
+    /// Default: a [`LocalIdentifyQueue`] for this client only.
+    ///
+    /// [`LocalIdentifyQueue`]: types::gateway::identify::LocalIdentifyQueue
+    pub fn identify_queue(&mut self, queue: Arc<dyn IdentifyQueue>) -> &mut Self {
+        self.identify_queue = queue;
+        self
+    }
+
     /// Launch the websocket connection, returning a tokio `mpsc` receiver.
-    /// over which [`DiscordEvent`]s will be dispatched.
+    /// over which [`DiscordEvent`]s will be dispatched, along with a
+    /// [`ShardHandle`] to send commands to the gateway.
+    ///
+    /// The connection is run in a task spawned on the runtime
+    /// this is called from, which owns the client from then on.
     ///
     /// [`DiscordEvent`]: gateway::payloads::DiscordEvent
     /// Handshakes, reconnects, authentication and heartbeats will
     /// be handled by this function and *not* passed to the user.
     //#[instrument(skip(self))]
-    pub async fn login(&'static mut self)
-                       -> Receiver<DiscordEvent> {
-        let rt = Builder::new().threaded_scheduler().build().expect("Failed to start runtime.");
-        let (mut tx, mut rx) = mpsc::channel(250);
-        let (mut stream, _) = connect_async(&self.url).await.expect("Failed to connect.");
-        let barrier = Arc::new(Barrier::new(2));
-        let (mut cache_tx, mut cache_rx) = mpsc::channel(125);
-        
-        // spawn the thread which manages the received
-        // messages.
-        rt.spawn(async move {
-            while let Some(val) = stream.next().await {
-                if val.is_ok() {
-                    let msg = val.unwrap();
-                    if msg.is_text() {
-                        let text = msg.to_text().expect("failed to decode message received");
-                        let data: GatewayMessages = serde_json::from_str(text).expect("Failed to convert to Rust object");
-                        match data {
-                            GatewayMessages::Others(v) => {
-                                match v.op {
-                                    OPCode::Hello => {
-                                        match v.d {
-                                            GatewayPayloadObjects::Hello(n) => {
-                                                self.heartbeat_interval = 
-                                                    Some(n.heartbeat_interval);
-
-                                                    stream.send(
-                                                        Message::Text(
-                                                            serde_json::to_string(self.identify_object).unwrap()
-                                                        )
-                                                    ).await.expect("failed to send identify object");
-
-
-                                            },
-                                            _ => {}
-                                        }
-                                    },
-                                    _ => handle_ws_payload(v).await,
-                                }
-                            },
-                            GatewayMessages::Ready(r) => {
-                                for g in r.guilds {
-                                    let (tmp_tx, tmp_rx) = oneshot::channel();
-                                    cache_tx.send(
-                                        CacheRequest::new(
-                                            CacheAction::Push(
-                                                (
-                                                    g.id, 
-                                                    DiscordEvent::GuildCreate(
-                                                        GuildType::Partial(g)
-                                                    )
-                                                )
-                                            ),
-                                            CacheType::Guild,
-                                            tmp_tx
-                                        )
-                                    ).await.map_err(|_| "failed to write to cache (dead?)").unwrap();
-                                    tmp_rx.await.ok().unwrap()
-                                }
-                            },
-                        }
-                    } else if msg.is_binary() {
-                        let data = msg.into_data();
-                        if &data[data.len()-4..] == b"\x00\x00\xff\xff" {
-                            let decompressed = 
-                            decompress_to_vec(&data).expect("Failed to decompress!");
-                            let string = String::from_utf8(decompressed).expect("Failed to convert into string!");
-                        }
-                    }
+    pub async fn login(mut self)
+                       -> (ShardHandle, Receiver<Result<DiscordEvent, DerustError>>) {
+        let handle = ShardHandle::new(self.command_tx.clone(), self.pending_chunks.clone());
+        let (mut tx, rx) = mpsc::channel(250);
+
+        // spawn the task which manages the connection, reconnecting
+        // and resuming every time the session dies or gets zombied.
+        tokio::spawn(async move {
+            loop {
+                match self.run_session(&mut tx).await {
+                    SessionEnd::Dropped => {
+                        delay_for(Duration::from_secs(RECONNECT_DELAY)).await;
+                    },
+                    SessionEnd::Reconnect => {},
+                    SessionEnd::Shutdown => break,
                 }
             }
         });
 
-        // heartbeat thread
-        rt.spawn(async move {
-            barrier.wait().await;
-                        
-        });
+        (handle, rx)
+    }
+
+    /// Run a single connection to the gateway until it either gets
+    /// closed or zombied, that is, the gateway didn't acknowledge
+    /// our last heartbeat before it was time to send the next one.
+    ///
+    /// If we already have a session, it will be resumed instead of
+    /// identifying again, so that the events we missed get replayed.
+    async fn run_session(
+        &mut self,
+        tx: &mut Sender<Result<DiscordEvent, DerustError>>,
+    ) -> SessionEnd {
+        let mut url = format!("{}/?v={}&encoding=json", self.url, GATEWAY_VERSION);
+        // a new context is required for every connection.
+        let mut decompressor = if self.identify_object.transport_compression {
+            url.push_str("&compress=zlib-stream");
+            Decompressor::Stream(ZlibStream::new())
+        } else {
+            Decompressor::Payload
+        };
+        let (mut stream, _) = match connect_async(url.as_str()).await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Failed to connect to the gateway: {}", e);
+                return SessionEnd::Dropped;
+            }
+        };
+        // the heartbeat thread stops by itself once this
+        // receiver gets dropped at the end of the session.
+        let (beat_tx, mut beat_rx) = mpsc::channel(1);
+        self.heartbeat_acked = true;
+        self.identified = false;
+        self.ratelimiter = SendRatelimiter::new();
+        // set once the gateway said hello and we have to identify,
+        // until the identify queue lets us do so.
+        let mut identify_turn: Option<IdentifyTurn> = None;
 
-        // cache requests thread
-        rt.spawn(async move {
+        loop {
+            let val = tokio::select! {
+                val = stream.next() => val,
+                _ = wait_turn(&mut identify_turn), if identify_turn.is_some() => {
+                    identify_turn = None;
+                    if let Err(e) = send_payload(&mut stream, OPCode::Identify, &self.identify_object).await {
+                        warn!("Failed to identify: {}", e);
+                        return SessionEnd::Dropped;
+                    }
+                    continue;
+                },
+                _ = beat_rx.recv() => {
+                    if !self.heartbeat_acked {
+                        warn!("Heartbeat was not acknowledged, reconnecting.");
+                        let _ = stream.close(Some(resume_close_frame())).await;
+                        return SessionEnd::Dropped;
+                    }
+                    self.heartbeat_acked = false;
+                    trace!("Sending heartbeat with sequence {:?}", self.seq);
+                    self.ratelimiter.take_reserved();
+                    if let Err(e) = send_payload(&mut stream, OPCode::Heartbeat, self.seq).await {
+                        warn!("Failed to send heartbeat: {}", e);
+                        return SessionEnd::Dropped;
+                    }
+                    continue;
+                },
+                // commands queued by the user are only sent once we have
+                // identified, as Discord closes the connection otherwise,
+                // and when the ratelimiter allows it.
+                Some(command) = next_command(&mut self.unsent_command, &mut self.command_rx),
+                    if self.identified && self.ratelimiter.can_send() => {
+                    self.ratelimiter.take();
+                    if let Err(e) = stream.send(Message::Text(command.clone())).await {
+                        warn!("Failed to send command: {}", e);
+                        self.unsent_command = Some(command);
+                        return SessionEnd::Dropped;
+                    }
+                    continue;
+                },
+                _ = delay_for(self.ratelimiter.available_in()),
+                    if self.identified && !self.ratelimiter.can_send() => {
+                    continue;
+                },
+            };
+            let msg = match val {
+                Some(Ok(msg)) => msg,
+                Some(Err(e)) => {
+                    warn!("Gateway connection errored: {}", e);
+                    return SessionEnd::Dropped;
+                },
+                None => {
+                    warn!("Gateway connection closed.");
+                    return SessionEnd::Dropped;
+                },
+            };
+            let text = match msg {
+                Message::Close(frame) => {
+                    return self.handle_close(frame.map(|f| u16::from(f.code)), tx).await;
+                },
+                Message::Text(text) => text,
+                Message::Binary(data) => match decompressor.decompress(&data) {
+                    Ok(Some(text)) => text,
+                    // the rest of the payload is in the next frames.
+                    Ok(None) => continue,
+                    Err(e) => {
+                        warn!("Failed to decompress payload: {:?}, reconnecting.", e);
+                        return SessionEnd::Dropped;
+                    },
+                },
+                _ => continue,
+            };
+            // the sequence number is kept even if the payload
+            // object can't be decoded, or resuming would replay it.
+            let data = match GatewayPayload::parse(&text).and_then(|payload| {
+                if let Some(s) = payload.s {
+                    self.seq = Some(s);
+                }
+                GatewayMessages::from_payload(payload, &text)
+            }) {
+                Ok(data) => data,
+                Err(e) => {
+                    warn!("Failed to decode payload: {:?}", e);
+                    let _ = tx.send(Err(e)).await;
+                    continue;
+                },
+            };
+            match data {
+                GatewayMessages::Hello(n) => {
+                    self.heartbeat_interval =
+                        Some(n.heartbeat_interval);
+                    tokio::spawn(heartbeat(
+                        Duration::from_millis(n.heartbeat_interval),
+                        beat_tx.clone()
+                    ));
+                    self.ratelimiter.reserve_for_heartbeats(n.heartbeat_interval);
+                    self.ratelimiter.take_reserved();
 
-        });
+                    match (&self.session_id, self.seq) {
+                        (Some(session_id), Some(seq)) => {
+                            debug!("Resuming session {} at sequence {}", session_id, seq);
+                            let resume = ResumeObject {
+                                token: self.identify_object.token.clone(),
+                                session_id: session_id.clone(),
+                                seq,
+                            };
+                            if let Err(e) = send_payload(&mut stream, OPCode::Resume, resume).await {
+                                warn!("Failed to resume: {}", e);
+                                return SessionEnd::Dropped;
+                            }
+                        },
+                        // identifying waits for our turn, during
+                        // which the heartbeats keep being sent.
+                        _ => {
+                            let queue = self.identify_queue.clone();
+                            let shard_id = self.identify_object.shard.map_or(0, |s| s[0]);
+                            identify_turn = Some(Box::pin(async move { queue.wait(shard_id).await }));
+                        },
+                    }
+                },
+                GatewayMessages::HeartbeatACK => {
+                    self.heartbeat_acked = true;
+                },
+                // the gateway may request a heartbeat at any
+                // time, which we have to answer immediately.
+                GatewayMessages::Heartbeat => {
+                    self.ratelimiter.take_reserved();
+                    if let Err(e) = send_payload(&mut stream, OPCode::Heartbeat, self.seq).await {
+                        warn!("Failed to send heartbeat: {}", e);
+                        return SessionEnd::Dropped;
+                    }
+                },
+                GatewayMessages::Reconnect => {
+                    debug!("Gateway requested a reconnect.");
+                    let _ = stream.close(Some(resume_close_frame())).await;
+                    return SessionEnd::Reconnect;
+                },
+                GatewayMessages::InvalidSession(resumable) => {
+                    if resumable {
+                        debug!("Session could not be resumed yet, retrying.");
+                        let _ = stream.close(Some(resume_close_frame())).await;
+                        return SessionEnd::Dropped;
+                    }
+                    self.session_id = None;
+                    self.seq = None;
+                    if !self.identify_object.auto_reidentify {
+                        error!("Session was invalidated and auto_reidentify is disabled, shutting down.");
+                        let _ = stream.close(None).await;
+                        let _ = tx.send(Err(DerustError::SessionInvalidated)).await;
+                        return SessionEnd::Shutdown;
+                    }
+                    // identify again on a new connection, once
+                    // the identify queue lets us do so.
+                    debug!("Session was invalidated, identifying again.");
+                    let _ = stream.close(None).await;
+                    return SessionEnd::Dropped;
+                },
+                GatewayMessages::Dispatch(_, event) => {
+                    // the user sees the caches as they are after the event.
+                    #[cfg(feature = "cache")]
+                    self.cache.update(&event);
+                    match &event {
+                        DiscordEvent::Ready(r) => self.handle_ready(r),
+                        DiscordEvent::Resumed => self.identified = true,
+                        DiscordEvent::GuildMembersChunk(chunk) => self.pending_chunks.feed(chunk),
+                        _ => {},
+                    }
+                    let _ = tx.send(Ok(event)).await;
+                },
+            }
+        }
+    }
 
-        rx
+    /// Decide what to do after the gateway closed the connection
+    /// with `code`, see [`CloseCode`] for what each one means.
+    ///
+    /// Fatal codes are sent to the user as a [`DerustError::GatewayClosed`]
+    /// and shut down the client, since reconnecting wouldn't help.
+    async fn handle_close(
+        &mut self,
+        code: Option<u16>,
+        tx: &mut Sender<Result<DiscordEvent, DerustError>>,
+    ) -> SessionEnd {
+        let code = match code.and_then(CloseCode::from_code) {
+            Some(code) => code,
+            None => {
+                warn!("Gateway closed the connection with code {:?}, reconnecting.", code);
+                return SessionEnd::Dropped;
+            },
+        };
+        if code.is_fatal() {
+            error!("Gateway closed the connection with {:?}, shutting down.", code);
+            let _ = tx.send(Err(DerustError::GatewayClosed(code))).await;
+            return SessionEnd::Shutdown;
+        }
+        if !code.can_resume() {
+            self.session_id = None;
+            self.seq = None;
+        }
+        warn!("Gateway closed the connection with {:?}, reconnecting.", code);
+        SessionEnd::Dropped
+    }
+
+    /// Store the session, so that it can be resumed later on.
+    fn handle_ready(&mut self, r: &ReadyObject) {
+        self.session_id = Some(r.session_id.clone());
+        self.identified = true;
     }
 }
 
-async fn handle_ws_payload(msg: GatewayPayload) {
-    match msg.op {
-        OPCode::Dispatch => {
-            match msg.d {
-                _ => {
-                    
-                }
-            }
-        }
-        _ => {}
+impl Drop for Client {
+    fn drop(&mut self) {
+        // the handles share the pending requests, which would
+        // otherwise wait for chunks that will never arrive.
+        self.pending_chunks.cancel_all();
+    }
+}
+
+/// The reason a single gateway connection has ended.
+enum SessionEnd {
+    /// The connection dropped or got zombied, reconnect after a delay.
+    Dropped,
+    /// The gateway asked us to reconnect, do it immediately.
+    Reconnect,
+    /// The session can't be continued, stop the client.
+    Shutdown,
+}
+
+/// Close frame used when we intend to resume the session later on.
+///
+/// Closing with a normal (1000 or 1001) code invalidates the
+/// session, so we use a code from the private 4000 range instead.
+fn resume_close_frame() -> CloseFrame<'static> {
+    CloseFrame {
+        code: TungsteniteCloseCode::Library(4000),
+        reason: "".into(),
+    }
+}
+
+/// The next command to send, the one which failed
+/// to be sent coming before the queued ones.
+async fn next_command(unsent: &mut Option<String>, rx: &mut Receiver<String>) -> Option<String> {
+    match unsent.take() {
+        Some(command) => Some(command),
+        None => rx.recv().await,
+    }
+}
+
+/// Wait for our turn in the identify queue, if we are in it.
+async fn wait_turn(turn: &mut Option<IdentifyTurn>) {
+    match turn {
+        Some(turn) => turn.await,
+        None => future::pending().await,
+    }
+}
+
+/// Send a payload with the specified OPCode and
+/// payload object `d` over the gateway connection.
+async fn send_payload<S, T>(sink: &mut S, op: OPCode, d: T) -> Result<(), S::Error>
+    where S: Sink<Message> + Unpin, T: Serialize {
+    // our payload objects always serialize.
+    let payload = serde_json::to_string(&SendPayload { op, d })
+        .expect("Failed to serialize payload");
+    sink.send(Message::Text(payload)).await
+}
+
+/// Tell the session to send a heartbeat every `interval`.
+///
+/// The first one is delayed by a random fraction of the
+/// interval, as recommended by Discord, so that clients
+/// don't all heartbeat at once after an outage.
+async fn heartbeat(interval: Duration, mut tx: Sender<()>) {
+    delay_for(interval.mul_f64(rand::random::<f64>())).await;
+    while tx.send(()).await.is_ok() {
+        delay_for(interval).await;
     }
 }
@@ -0,0 +1,111 @@
+//! # Gateway compression
+//! Discord can compress what it sends over the gateway
+//! in two different ways:
+//!
+//! * Payload compression, enabled with [`IdentifyObject::compress`],
+//!   where some of the larger payloads are sent as a standalone
+//!   zlib-compressed binary frame.
+//!
+//! * Transport compression, enabled with [`IdentifyObject::transport_compression`],
+//!   where the *whole* connection is one zlib stream, and a payload
+//!   can be split over several binary frames.
+//!
+//! [`IdentifyObject::compress`]: super::builder::IdentifyObject::compress
+//! [`IdentifyObject::transport_compression`]: super::builder::IdentifyObject::transport_compression
+use miniz_oxide::inflate::decompress_to_vec_zlib;
+use miniz_oxide::inflate::stream::{inflate, InflateState};
+use miniz_oxide::{DataFormat, MZError, MZFlush};
+use crate::types::error::DerustError;
+
+/// Every payload sent with `zlib-stream` ends with a
+/// zlib `SYNC_FLUSH`, which is these 4 bytes.
+pub const ZLIB_SUFFIX: &[u8] = b"\x00\x00\xff\xff";
+
+/// Size of the chunks in which the output is inflated.
+const CHUNK_SIZE: usize = 32 * 1024;
+
+/// Decompresses the binary frames received from the gateway,
+/// depending on the compression that was chosen.
+pub enum Decompressor {
+    /// Every binary frame is a complete zlib payload.
+    Payload,
+    /// Binary frames are parts of a single zlib stream.
+    Stream(ZlibStream),
+}
+
+impl Decompressor {
+    /// Decompress a binary frame, returning the payload once
+    /// it has been completely received.
+    pub fn decompress(&mut self, data: &[u8]) -> Result<Option<String>, DerustError> {
+        match self {
+            Decompressor::Payload => {
+                let decompressed = decompress_to_vec_zlib(data)
+                    .map_err(|_| DerustError::DecompressError)?;
+                String::from_utf8(decompressed)
+                    .map(Some)
+                    .map_err(|_| DerustError::DecompressError)
+            },
+            Decompressor::Stream(stream) => stream.push(data),
+        }
+    }
+}
+
+/// A `zlib-stream` transport decompressor.
+///
+/// The inflate context is kept for the whole connection, since
+/// every payload references the data that was sent before it,
+/// so a new one has to be created for every new connection.
+pub struct ZlibStream {
+    state: Box<InflateState>,
+    buffer: Vec<u8>,
+}
+
+impl Default for ZlibStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ZlibStream {
+    pub fn new() -> Self {
+        Self {
+            state: InflateState::new_boxed(DataFormat::Zlib),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Push a binary frame into the stream.
+    ///
+    /// Frames are buffered until one ends with the [`ZLIB_SUFFIX`],
+    /// at which point the whole payload gets inflated and returned.
+    pub fn push(&mut self, frame: &[u8]) -> Result<Option<String>, DerustError> {
+        self.buffer.extend_from_slice(frame);
+        if !self.buffer.ends_with(ZLIB_SUFFIX) {
+            return Ok(None);
+        }
+
+        let mut input = &self.buffer[..];
+        let mut output = Vec::with_capacity(self.buffer.len() * 4);
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            let res = inflate(&mut self.state, input, &mut chunk, MZFlush::Sync);
+            input = &input[res.bytes_consumed..];
+            output.extend_from_slice(&chunk[..res.bytes_written]);
+            match res.status {
+                Ok(_) => {},
+                // no progress could be made because everything
+                // has already been inflated.
+                Err(MZError::Buf) if input.is_empty() => break,
+                Err(_) => return Err(DerustError::DecompressError),
+            }
+            if input.is_empty() && res.bytes_written < CHUNK_SIZE {
+                break;
+            }
+        }
+        self.buffer.clear();
+
+        String::from_utf8(output)
+            .map(Some)
+            .map_err(|_| DerustError::DecompressError)
+    }
+}
@@ -0,0 +1,202 @@
+//! # Gateway event objects
+//! The payload objects of the dispatched events which
+//! don't correspond to an already existing type, such as
+//! the ones that only contain the IDs of what was deleted.
+//!
+//! They are all wrapped by their [`DiscordEvent`] variant.
+//!
+//! [`DiscordEvent`]: super::payloads::DiscordEvent
+use serde::Deserialize;
+use crate::types::{Snowflake, Timestamp};
+use crate::types::user::User;
+use crate::types::role::Role;
+use crate::types::guild::GuildMember;
+use crate::types::message::{Attachment, Embed, Emoji, MentionedUsers};
+use crate::types::invite::TargetUserType;
+use super::activity::PresenceUpdate;
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ChannelPinsUpdate {
+    pub guild_id: Option<Snowflake>,
+    pub channel_id: Snowflake,
+    pub last_pin_timestamp: Option<Timestamp>,
+}
+
+/// Sent for both `GUILD_BAN_ADD` and `GUILD_BAN_REMOVE`.
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct GuildBan {
+    pub guild_id: Snowflake,
+    pub user: User,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct GuildEmojisUpdate {
+    pub guild_id: Snowflake,
+    pub emojis: Vec<Emoji>,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct GuildIntegrationsUpdate {
+    pub guild_id: Snowflake,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct GuildMemberAdd {
+    pub guild_id: Snowflake,
+    #[serde(flatten)]
+    pub member: GuildMember,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct GuildMemberRemove {
+    pub guild_id: Snowflake,
+    pub user: User,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct GuildMemberUpdate {
+    pub guild_id: Snowflake,
+    pub roles: Vec<Snowflake>,
+    pub user: User,
+    pub nick: Option<String>,
+    pub premium_since: Option<Timestamp>,
+}
+
+/// Sent in response to a `RequestGuildMembers` payload,
+/// split in chunks of up to 1000 members.
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct GuildMembersChunk {
+    pub guild_id: Snowflake,
+    pub members: Vec<GuildMember>,
+    pub chunk_index: u32,
+    pub chunk_count: u32,
+    /// IDs that were requested but weren't found.
+    pub not_found: Option<Vec<Snowflake>>,
+    pub presences: Option<Vec<PresenceUpdate>>,
+    pub nonce: Option<String>,
+}
+
+/// Sent for both `GUILD_ROLE_CREATE` and `GUILD_ROLE_UPDATE`.
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct GuildRole {
+    pub guild_id: Snowflake,
+    pub role: Role,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct GuildRoleDelete {
+    pub guild_id: Snowflake,
+    pub role_id: Snowflake,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct InviteCreate {
+    pub channel_id: Snowflake,
+    pub code: String,
+    pub created_at: Timestamp,
+    pub guild_id: Option<Snowflake>,
+    pub inviter: Option<User>,
+    pub max_age: i32,
+    pub max_uses: i32,
+    pub target_user: Option<User>,
+    pub target_user_type: Option<TargetUserType>,
+    pub temporary: bool,
+    pub uses: i32,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct InviteDelete {
+    pub channel_id: Snowflake,
+    pub guild_id: Option<Snowflake>,
+    pub code: String,
+}
+
+/// Message edits only contain the fields that were
+/// changed, apart from the IDs.
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct MessageUpdate {
+    pub id: Snowflake,
+    pub channel_id: Snowflake,
+    pub guild_id: Option<Snowflake>,
+    pub author: Option<User>,
+    pub content: Option<String>,
+    pub edited_timestamp: Option<Timestamp>,
+    pub mention_everyone: Option<bool>,
+    pub mentions: Option<Vec<MentionedUsers>>,
+    pub mention_roles: Option<Vec<Snowflake>>,
+    pub attachments: Option<Vec<Attachment>>,
+    pub embeds: Option<Vec<Embed>>,
+    pub pinned: Option<bool>,
+    pub flags: Option<i32>,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct MessageDelete {
+    pub id: Snowflake,
+    pub channel_id: Snowflake,
+    pub guild_id: Option<Snowflake>,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct MessageDeleteBulk {
+    pub ids: Vec<Snowflake>,
+    pub channel_id: Snowflake,
+    pub guild_id: Option<Snowflake>,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct MessageReactionAdd {
+    pub user_id: Snowflake,
+    pub channel_id: Snowflake,
+    pub message_id: Snowflake,
+    pub guild_id: Option<Snowflake>,
+    pub member: Option<GuildMember>,
+    pub emoji: Emoji,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct MessageReactionRemove {
+    pub user_id: Snowflake,
+    pub channel_id: Snowflake,
+    pub message_id: Snowflake,
+    pub guild_id: Option<Snowflake>,
+    pub emoji: Emoji,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct MessageReactionRemoveAll {
+    pub channel_id: Snowflake,
+    pub message_id: Snowflake,
+    pub guild_id: Option<Snowflake>,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct MessageReactionRemoveEmoji {
+    pub channel_id: Snowflake,
+    pub message_id: Snowflake,
+    pub guild_id: Option<Snowflake>,
+    pub emoji: Emoji,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct TypingStart {
+    pub channel_id: Snowflake,
+    pub guild_id: Option<Snowflake>,
+    pub user_id: Snowflake,
+    pub timestamp: u64, // unix timestamp in seconds
+    pub member: Option<GuildMember>,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct VoiceServerUpdate {
+    pub token: String,
+    pub guild_id: Snowflake,
+    /// `None` when the voice server is unavailable.
+    pub endpoint: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct WebhooksUpdate {
+    pub guild_id: Snowflake,
+    pub channel_id: Snowflake,
+}
@@ -0,0 +1,156 @@
+use serde::Serialize;
+use tokio::sync::mpsc::Sender;
+use tokio::time::{self, Duration};
+use crate::types::Snowflake;
+use crate::types::error::DerustError;
+use super::activity::StatusUpdate;
+use super::chunks::{MemberChunks, PendingChunks};
+use super::opcodes::OPCode;
+use super::payloads::{RequestGuildMembersObject, SendPayload, VoiceStateUpdateObject};
+
+/// How long the nonce of a member request can be, in bytes.
+const MAX_NONCE_LENGTH: usize = 32;
+
+/// How many members can be requested by ID at once.
+const MAX_USER_IDS: usize = 100;
+
+/// A handle to a running [`Client`], used to send commands
+/// to the gateway over its connection.
+///
+/// It can be cloned freely and moved across tasks. The commands
+/// are queued and sent in order, within the ratelimit of the
+/// gateway, and are kept across reconnects until they get sent.
+///
+/// [`Client`]: super::client::Client
+#[derive(Clone, Debug)]
+pub struct ShardHandle {
+    commands: Sender<String>,
+    pending_chunks: PendingChunks,
+}
+
+/// Which members of a guild to request
+/// with [`ShardHandle::request_guild_members`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MemberFilter {
+    /// Members whose username starts with this string,
+    /// or all of them if it is empty.
+    Query(String),
+    /// The members with these user IDs.
+    UserIds(Vec<Snowflake>),
+}
+
+impl ShardHandle {
+    pub(crate) fn new(commands: Sender<String>, pending_chunks: PendingChunks) -> Self {
+        ShardHandle { commands, pending_chunks }
+    }
+
+    /// Update the presence of the bot, such
+    /// as its status and the activity shown.
+    pub async fn update_presence(&self, presence: StatusUpdate) -> Result<(), DerustError> {
+        self.send(OPCode::PresenceUpdate, presence).await
+    }
+
+    /// Join, move to or leave (with a `channel_id` of `None`)
+    /// a voice channel of the guild `guild_id`.
+    pub async fn update_voice_state(
+        &self,
+        guild_id: Snowflake,
+        channel_id: Option<Snowflake>,
+        self_mute: bool,
+        self_deaf: bool,
+    ) -> Result<(), DerustError> {
+        self.send(OPCode::VoiceStateUpdate, VoiceStateUpdateObject {
+            guild_id,
+            channel_id,
+            self_mute,
+            self_deaf,
+        }).await
+    }
+
+    /// Request the members of the guild `guild_id` matching `filter`,
+    /// which will be dispatched as [`DiscordEvent::GuildMembersChunk`]s
+    /// carrying the same `nonce`.
+    ///
+    /// A `limit` of 0 returns every member when querying an empty string,
+    /// which requires the `GUILD_MEMBERS` intent, as does requesting
+    /// `presences` with the `GUILD_PRESENCES` one.
+    ///
+    /// At most 100 user IDs can be requested at once,
+    /// and the `nonce` can be up to 32 bytes long.
+    ///
+    /// [`DiscordEvent::GuildMembersChunk`]: super::payloads::DiscordEvent::GuildMembersChunk
+    pub async fn request_guild_members(
+        &self,
+        guild_id: Snowflake,
+        filter: MemberFilter,
+        limit: u32,
+        presences: bool,
+        nonce: Option<String>,
+    ) -> Result<(), DerustError> {
+        if let Some(nonce) = &nonce {
+            if nonce.len() > MAX_NONCE_LENGTH {
+                return Err(DerustError::InvalidInput(
+                    format!("nonce must be at most {} bytes long", MAX_NONCE_LENGTH)
+                ));
+            }
+        }
+        let (query, user_ids) = match filter {
+            MemberFilter::Query(query) => (Some(query), None),
+            MemberFilter::UserIds(ids) if ids.len() > MAX_USER_IDS => {
+                return Err(DerustError::InvalidInput(
+                    format!("at most {} user IDs can be requested at once", MAX_USER_IDS)
+                ));
+            },
+            MemberFilter::UserIds(ids) => (None, Some(ids)),
+        };
+        self.send(OPCode::RequestGuildMembers, RequestGuildMembersObject {
+            guild_id,
+            query,
+            limit,
+            presences,
+            user_ids,
+            nonce,
+        }).await
+    }
+
+    /// Same as [`request_guild_members`](#method.request_guild_members),
+    /// but waiting for every chunk sent in response to the request and
+    /// returning all the members in them, which also get cached.
+    ///
+    /// Fails with [`DerustError::Timeout`] if they didn't
+    /// all arrive within `timeout`.
+    pub async fn collect_guild_members(
+        &self,
+        guild_id: Snowflake,
+        filter: MemberFilter,
+        limit: u32,
+        presences: bool,
+        timeout: Duration,
+    ) -> Result<MemberChunks, DerustError> {
+        let nonce = format!("{:016x}", rand::random::<u64>());
+        let chunks = self.pending_chunks.register(nonce.clone());
+        if let Err(e) = self.request_guild_members(
+            guild_id, filter, limit, presences, Some(nonce.clone())
+        ).await {
+            self.pending_chunks.cancel(&nonce);
+            return Err(e);
+        }
+        match time::timeout(timeout, chunks).await {
+            Ok(Ok(chunks)) => Ok(chunks),
+            // the client shut down with the request still pending.
+            Ok(Err(_)) => Err(DerustError::ClientShutDown),
+            Err(_) => {
+                self.pending_chunks.cancel(&nonce);
+                Err(DerustError::Timeout)
+            },
+        }
+    }
+
+    /// Queue a payload to be sent by the client.
+    async fn send<T: Serialize>(&self, op: OPCode, d: T) -> Result<(), DerustError> {
+        let text = serde_json::to_string(&SendPayload { op, d })
+            .map_err(|e| DerustError::InvalidInput(e.to_string()))?;
+        self.commands.clone().send(text).await
+            .map_err(|_| DerustError::ClientShutDown)
+    }
+}
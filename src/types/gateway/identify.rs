@@ -0,0 +1,69 @@
+//! # Identify queue
+//! Discord only allows `max_concurrency` shards to identify
+//! every 5 seconds, where the shard `shard_id` falls into the
+//! rate limit bucket `shard_id % max_concurrency`.
+//!
+//! Every [`Client`] waits on an [`IdentifyQueue`] before identifying.
+//! If all your shards run in the same process, the default
+//! [`LocalIdentifyQueue`] is enough, otherwise implement the trait
+//! on top of something shared between the processes, such as Redis.
+//!
+//! [`Client`]: super::client::Client
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use tokio::time::{delay_until, Duration, Instant};
+
+/// How long a bucket is taken after a shard identifies.
+pub const IDENTIFY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Decides when each shard is allowed to identify.
+///
+/// An example implementation which lets every shard identify
+/// right away (which you should obviously not do) would be:
+/// ```rust
+/// use async_trait::async_trait;
+/// use derust::types::gateway::identify::IdentifyQueue;
+///
+/// struct NoQueue;
+///
+/// #[async_trait]
+/// impl IdentifyQueue for NoQueue {
+///     async fn wait(&self, _shard_id: u16) {}
+/// }
+/// ```
+#[async_trait]
+pub trait IdentifyQueue: Send + Sync {
+    /// Wait until the shard `shard_id` is allowed to identify,
+    /// the shard is assumed to identify right after it returns.
+    async fn wait(&self, shard_id: u16);
+}
+
+/// An in-memory [`IdentifyQueue`], for shards running in this process only.
+pub struct LocalIdentifyQueue {
+    /// When each bucket was last used to identify.
+    buckets: Vec<Mutex<Option<Instant>>>,
+}
+
+impl LocalIdentifyQueue {
+    /// Construct a queue with `max_concurrency` buckets, as
+    /// returned in the `session_start_limit` of `/gateway/bot`.
+    pub fn new(max_concurrency: u16) -> Self {
+        Self {
+            buckets: (0..max_concurrency.max(1)).map(|_| Mutex::new(None)).collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl IdentifyQueue for LocalIdentifyQueue {
+    async fn wait(&self, shard_id: u16) {
+        let bucket = &self.buckets[shard_id as usize % self.buckets.len()];
+        // the lock is held while waiting, so that the shards
+        // of the same bucket identify one after the other.
+        let mut last = bucket.lock().await;
+        if let Some(last) = *last {
+            delay_until(last + IDENTIFY_INTERVAL).await;
+        }
+        *last = Some(Instant::now());
+    }
+}
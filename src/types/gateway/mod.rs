@@ -7,8 +7,15 @@
 //! to initialize the connection to the gateway.
 pub mod builder;
 pub mod payloads;
+pub mod events;
 pub mod opcodes;
 pub mod activity;
 pub mod client;
+pub mod chunks;
+pub mod handle;
+pub mod compression;
+pub mod identify;
+pub mod ratelimit;
+pub mod shard;
 #[cfg(feature = "cache")]
 pub mod cache;
\ No newline at end of file
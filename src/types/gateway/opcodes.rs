@@ -4,13 +4,14 @@
 //!
 //! You can find additional information about each one
 //! on the [Developer documentation for Discord](https://discord.dev/)
-use serde::{Serialize, Deserialize};
+use serde_repr::{Serialize_repr, Deserialize_repr};
 
 /// These are the OPCodes used by both the client and
 /// the gateway server to communicate.
 ///
 /// They are found in the `op` header of each payload.
-#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub enum OPCode {
     Dispatch = 0, // y
     Heartbeat = 1, // n
@@ -28,11 +29,14 @@ pub enum OPCode {
 /// These are the close codes that will be sent
 /// by the server when you get disconnected.
 ///
-/// All of them should get automatically handled,
-/// either by panicking and exiting with the
-/// OPCode as the status, or by just attempting
-/// to resume the session or just starting a new one.
-#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+/// All of them get automatically handled, either by
+/// attempting to resume the session, by starting a new one,
+/// or, for the fatal ones, by sending a [`DerustError::GatewayClosed`]
+/// through the event receiver and shutting down the client.
+///
+/// [`DerustError::GatewayClosed`]: crate::types::error::DerustError::GatewayClosed
+#[derive(Serialize_repr, Deserialize_repr, Clone, Debug, Eq, PartialEq)]
+#[repr(u16)]
 pub enum CloseCode {
     /// Unknown reason for closing. Reconnect.
     Unknown = 4000,
@@ -76,4 +80,50 @@ pub enum CloseCode {
     ///
     /// b. You aren't whitelisted for it.
     DisallowedIntent = 4014,
-}
\ No newline at end of file
+}
+
+impl CloseCode {
+    /// Get the `CloseCode` corresponding to the code
+    /// of a websocket close frame, if it is one.
+    pub fn from_code(code: u16) -> Option<Self> {
+        match code {
+            4000 => Some(CloseCode::Unknown),
+            4001 => Some(CloseCode::UnknownOPCode),
+            4002 => Some(CloseCode::DecodeError),
+            4003 => Some(CloseCode::Unauthenticated),
+            4004 => Some(CloseCode::AuthFailed),
+            4005 => Some(CloseCode::AlreadyAuthenticated),
+            4007 => Some(CloseCode::InvalidSeq),
+            4008 => Some(CloseCode::Ratelimited),
+            4009 => Some(CloseCode::Timeout),
+            4010 => Some(CloseCode::InvalidShard),
+            4011 => Some(CloseCode::ShardingRequired),
+            4012 => Some(CloseCode::InvalidVersion),
+            4013 => Some(CloseCode::InvalidIntent),
+            4014 => Some(CloseCode::DisallowedIntent),
+            _ => None,
+        }
+    }
+
+    /// Whether reconnecting would just get us closed again,
+    /// because the problem lies in our configuration.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self,
+            CloseCode::AuthFailed
+            | CloseCode::InvalidShard
+            | CloseCode::ShardingRequired
+            | CloseCode::InvalidVersion
+            | CloseCode::InvalidIntent
+            | CloseCode::DisallowedIntent)
+    }
+
+    /// Whether the session is still valid after being closed,
+    /// so that it can be resumed instead of starting a new one.
+    pub fn can_resume(&self) -> bool {
+        matches!(self,
+            CloseCode::Unknown
+            | CloseCode::UnknownOPCode
+            | CloseCode::DecodeError
+            | CloseCode::AlreadyAuthenticated)
+    }
+}
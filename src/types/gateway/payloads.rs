@@ -1,19 +1,44 @@
 use serde::{Serialize, Deserialize};
 use super::opcodes::OPCode;
-use super::builder::IdentifyObject;
+use crate::types::error::DerustError;
+use serde_json::{from_str, value::RawValue};
 use crate::types::user::User;
 use crate::types::channel::Channel;
-use crate::types::{CachedTypes, guild::{Guild, PartialGuild}};
+use crate::types::{CachedTypes, Snowflake, guild::{Guild, PartialGuild}};
+use crate::types::message::Message;
+use crate::types::voice::VoiceState;
+use super::activity::PresenceUpdate;
+use super::events::*;
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
-pub struct GatewayPayload {
+/// The envelope of every payload received from the gateway,
+/// where `d` is kept as raw JSON until `op` and `t` tell us
+/// what it has to be deserialized into.
+#[derive(Deserialize, Debug)]
+pub struct GatewayPayload<'a> {
     pub op: OPCode, // opcode
-    pub d: GatewayPayloadObjects, // payload object
+    #[serde(borrow)]
+    pub d: Option<&'a RawValue>, // payload object, null for heartbeats and ACKs
     pub s: Option<u64>, // sequence number
     pub t: Option<String>, // event name
 }
 
-#[derive(Serialize, Deserialize, Eq, PartialEq, Debug)] // won't be serialized but it's required because of GatewayPayloadObjects
+impl<'a> GatewayPayload<'a> {
+    /// Read the envelope of a payload, such as to get its sequence
+    /// number even if its payload object can't be deserialized.
+    pub fn parse(text: &'a str) -> Result<Self, DerustError> {
+        serde_json::from_str(text).map_err(|e| DerustError::decode(None, None, text, &e))
+    }
+}
+
+/// A payload sent by the client to the gateway,
+/// with `d` being any serializable payload object.
+#[derive(Serialize, Debug)]
+pub struct SendPayload<T: Serialize> {
+    pub op: OPCode,
+    pub d: T,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug)]
 pub struct ReadyObject {
     pub v: u8, // gateway version, currently 6
     pub user: User, // object of the User that connected
@@ -23,13 +48,121 @@ pub struct ReadyObject {
     pub shard: Option<Vec<u16>>
 }
 
+/// All the events dispatched by the gateway, named after
+/// the `t` field of the payload they were received in.
+///
+/// See the [Discord developer documentation](https://discord.com/developers/docs/topics/gateway#commands-and-events-gateway-events)
+/// for when each one of them is sent.
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum DiscordEvent {
     Ready(ReadyObject),
+    Resumed,
+    ChannelCreate(Channel),
+    ChannelUpdate(Channel),
+    ChannelDelete(Channel),
+    ChannelPinsUpdate(ChannelPinsUpdate),
     GuildCreate(GuildType),
-    
+    GuildUpdate(Guild),
+    /// Also sent when a guild becomes unavailable,
+    /// in which case `unavailable` is set.
+    GuildDelete(PartialGuild),
+    GuildBanAdd(GuildBan),
+    GuildBanRemove(GuildBan),
+    GuildEmojisUpdate(GuildEmojisUpdate),
+    GuildIntegrationsUpdate(GuildIntegrationsUpdate),
+    GuildMemberAdd(GuildMemberAdd),
+    GuildMemberRemove(GuildMemberRemove),
+    GuildMemberUpdate(GuildMemberUpdate),
+    GuildMembersChunk(GuildMembersChunk),
+    GuildRoleCreate(GuildRole),
+    GuildRoleUpdate(GuildRole),
+    GuildRoleDelete(GuildRoleDelete),
+    InviteCreate(InviteCreate),
+    InviteDelete(InviteDelete),
+    MessageCreate(Message),
+    MessageUpdate(MessageUpdate),
+    MessageDelete(MessageDelete),
+    MessageDeleteBulk(MessageDeleteBulk),
+    MessageReactionAdd(MessageReactionAdd),
+    MessageReactionRemove(MessageReactionRemove),
+    MessageReactionRemoveAll(MessageReactionRemoveAll),
+    MessageReactionRemoveEmoji(MessageReactionRemoveEmoji),
+    PresenceUpdate(PresenceUpdate),
+    TypingStart(TypingStart),
+    UserUpdate(User),
+    VoiceStateUpdate(VoiceState),
+    VoiceServerUpdate(VoiceServerUpdate),
+    WebhooksUpdate(WebhooksUpdate),
+    /// An event that isn't known by the library (yet), so
+    /// that new events don't break existing bots.
+    Unknown {
+        name: String,
+        raw: serde_json::Value,
+    },
+}
+
+impl DiscordEvent {
+    /// Deserialize the payload object `d` of a dispatch
+    /// into the event corresponding to its name `t`.
+    pub fn from_dispatch(name: &str, d: &RawValue) -> Result<Self, serde_json::Error> {
+        let d = d.get();
+        Ok(match name {
+            "READY" => DiscordEvent::Ready(from_str(d)?),
+            "RESUMED" => DiscordEvent::Resumed,
+            "CHANNEL_CREATE" => DiscordEvent::ChannelCreate(from_str(d)?),
+            "CHANNEL_UPDATE" => DiscordEvent::ChannelUpdate(from_str(d)?),
+            "CHANNEL_DELETE" => DiscordEvent::ChannelDelete(from_str(d)?),
+            "CHANNEL_PINS_UPDATE" => DiscordEvent::ChannelPinsUpdate(from_str(d)?),
+            "GUILD_CREATE" => match from_str(d) {
+                Ok(guild) => DiscordEvent::GuildCreate(GuildType::Full(guild)),
+                // unavailable guilds only contain their ID.
+                Err(e) => match from_str::<PartialGuild>(d) {
+                    Ok(guild) if guild.unavailable => {
+                        DiscordEvent::GuildCreate(GuildType::Partial(guild))
+                    },
+                    _ => return Err(e),
+                },
+            },
+            "GUILD_UPDATE" => DiscordEvent::GuildUpdate(from_str(d)?),
+            "GUILD_DELETE" => DiscordEvent::GuildDelete(from_str(d)?),
+            "GUILD_BAN_ADD" => DiscordEvent::GuildBanAdd(from_str(d)?),
+            "GUILD_BAN_REMOVE" => DiscordEvent::GuildBanRemove(from_str(d)?),
+            "GUILD_EMOJIS_UPDATE" => DiscordEvent::GuildEmojisUpdate(from_str(d)?),
+            "GUILD_INTEGRATIONS_UPDATE" => DiscordEvent::GuildIntegrationsUpdate(from_str(d)?),
+            "GUILD_MEMBER_ADD" => DiscordEvent::GuildMemberAdd(from_str(d)?),
+            "GUILD_MEMBER_REMOVE" => DiscordEvent::GuildMemberRemove(from_str(d)?),
+            "GUILD_MEMBER_UPDATE" => DiscordEvent::GuildMemberUpdate(from_str(d)?),
+            "GUILD_MEMBERS_CHUNK" => DiscordEvent::GuildMembersChunk(from_str(d)?),
+            "GUILD_ROLE_CREATE" => DiscordEvent::GuildRoleCreate(from_str(d)?),
+            "GUILD_ROLE_UPDATE" => DiscordEvent::GuildRoleUpdate(from_str(d)?),
+            "GUILD_ROLE_DELETE" => DiscordEvent::GuildRoleDelete(from_str(d)?),
+            "INVITE_CREATE" => DiscordEvent::InviteCreate(from_str(d)?),
+            "INVITE_DELETE" => DiscordEvent::InviteDelete(from_str(d)?),
+            "MESSAGE_CREATE" => DiscordEvent::MessageCreate(from_str(d)?),
+            "MESSAGE_UPDATE" => DiscordEvent::MessageUpdate(from_str(d)?),
+            "MESSAGE_DELETE" => DiscordEvent::MessageDelete(from_str(d)?),
+            "MESSAGE_DELETE_BULK" => DiscordEvent::MessageDeleteBulk(from_str(d)?),
+            "MESSAGE_REACTION_ADD" => DiscordEvent::MessageReactionAdd(from_str(d)?),
+            "MESSAGE_REACTION_REMOVE" => DiscordEvent::MessageReactionRemove(from_str(d)?),
+            "MESSAGE_REACTION_REMOVE_ALL" => DiscordEvent::MessageReactionRemoveAll(from_str(d)?),
+            "MESSAGE_REACTION_REMOVE_EMOJI" => DiscordEvent::MessageReactionRemoveEmoji(from_str(d)?),
+            "PRESENCE_UPDATE" => DiscordEvent::PresenceUpdate(from_str(d)?),
+            "TYPING_START" => DiscordEvent::TypingStart(from_str(d)?),
+            "USER_UPDATE" => DiscordEvent::UserUpdate(from_str(d)?),
+            "VOICE_STATE_UPDATE" => DiscordEvent::VoiceStateUpdate(from_str(d)?),
+            "VOICE_SERVER_UPDATE" => DiscordEvent::VoiceServerUpdate(from_str(d)?),
+            "WEBHOOKS_UPDATE" => DiscordEvent::WebhooksUpdate(from_str(d)?),
+            _ => DiscordEvent::Unknown {
+                name: name.to_owned(),
+                raw: from_str(d)?,
+            },
+        })
+    }
 }
 
-#[derive(Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::large_enum_variant)]
 pub enum GuildType {
     Full(Guild),
     Partial(PartialGuild)
@@ -42,16 +175,131 @@ pub struct HelloObject {
     pub heartbeat_interval: u64,
 }
 
+/// Sent instead of the [`IdentifyObject`] when reconnecting,
+/// so that the gateway replays the events that we missed
+/// since the sequence number `seq`.
+///
+/// [`IdentifyObject`]: super::builder::IdentifyObject
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug)]
-#[serde(untagged)]
-pub enum GatewayPayloadObjects {
-    Identify(IdentifyObject),
-    Hello(HelloObject),
+pub struct ResumeObject {
+    pub token: String,
+    pub session_id: String,
+    pub seq: u64,
 }
 
-#[derive(Serialize, Deserialize, Eq, PartialEq, Debug)]
-#[serde(untagged)]
+/// Sent to join, move between or leave voice channels,
+/// `channel_id` being `None` when leaving.
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct VoiceStateUpdateObject {
+    pub guild_id: Snowflake,
+    pub channel_id: Option<Snowflake>,
+    pub self_mute: bool,
+    pub self_deaf: bool,
+}
+
+/// Sent to request the members of a guild, which are then
+/// received in [`DiscordEvent::GuildMembersChunk`]s.
+///
+/// Only one of `query` and `user_ids` may be set.
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct RequestGuildMembersObject {
+    pub guild_id: Snowflake,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// 0 requests all the members when `query` is empty.
+    pub limit: u32,
+    pub presences: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_ids: Option<Vec<Snowflake>>,
+    /// Sent back in the chunks, to tell which request they answer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+/// A payload received from the gateway, with its payload object
+/// deserialized into the type corresponding to its `op` and `t`.
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum GatewayMessages {
-    Ready(ReadyObject),
-    Others(GatewayPayload),
-}
\ No newline at end of file
+    /// An event, along with its sequence number.
+    Dispatch(u64, DiscordEvent),
+    /// The gateway requested a heartbeat right away.
+    Heartbeat,
+    Reconnect,
+    /// Whether or not the invalidated session can be resumed.
+    InvalidSession(bool),
+    Hello(HelloObject),
+    HeartbeatACK,
+}
+
+impl GatewayMessages {
+    /// Decode a payload received from the gateway.
+    ///
+    /// Only the envelope is read at first, the payload object
+    /// then gets deserialized straight into the right type,
+    /// instead of serde trying every possible one in order.
+    pub fn decode(text: &str) -> Result<Self, DerustError> {
+        Self::from_payload(GatewayPayload::parse(text)?, text)
+    }
+
+    /// Deserialize the payload object of an envelope read out
+    /// of `text` by [`GatewayPayload::parse`], according to its
+    /// `op` and `t`.
+    pub fn from_payload(payload: GatewayPayload, text: &str) -> Result<Self, DerustError> {
+        let d = payload.d;
+        // the column of the error is the one in the payload object.
+        let decode_error = |d: &RawValue, e: serde_json::Error| {
+            DerustError::decode(Some(payload.op.clone()), payload.t.clone(), d.get(), &e)
+        };
+        let invalid = |message: &str| DerustError::DecodeError {
+            op: Some(payload.op.clone()),
+            t: payload.t.clone(),
+            message: String::from(message),
+            snippet: text.chars().take(200).collect(),
+        };
+        let missing = || invalid("missing payload object");
+        Ok(match payload.op {
+            OPCode::Dispatch => {
+                let (s, t) = match (payload.s, &payload.t) {
+                    (Some(s), Some(t)) => (s, t),
+                    _ => return Err(missing()),
+                };
+                let d = d.ok_or_else(missing)?;
+                let event = DiscordEvent::from_dispatch(t, d)
+                    .map_err(|e| decode_error(d, e))?;
+                GatewayMessages::Dispatch(s, event)
+            },
+            OPCode::Heartbeat => GatewayMessages::Heartbeat,
+            OPCode::Reconnect => GatewayMessages::Reconnect,
+            OPCode::InvalidSession => {
+                let d = d.ok_or_else(missing)?;
+                GatewayMessages::InvalidSession(from_str(d.get()).map_err(|e| decode_error(d, e))?)
+            },
+            OPCode::Hello => {
+                let d = d.ok_or_else(missing)?;
+                GatewayMessages::Hello(from_str(d.get()).map_err(|e| decode_error(d, e))?)
+            },
+            OPCode::HeartbeatACK => GatewayMessages::HeartbeatACK,
+            _ => return Err(invalid("unexpected OPCode sent by the gateway")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequence_is_read_when_the_payload_object_fails_to_decode() {
+        let text = r#"{"op":0,"s":42,"t":"MESSAGE_DELETE","d":{"id":"x"}}"#;
+        let payload = GatewayPayload::parse(text).unwrap();
+        assert_eq!(payload.s, Some(42));
+        match GatewayMessages::from_payload(payload, text) {
+            Err(DerustError::DecodeError { op, t, .. }) => {
+                assert_eq!(op, Some(OPCode::Dispatch));
+                assert_eq!(t.as_deref(), Some("MESSAGE_DELETE"));
+            },
+            _ => panic!("the payload object should fail to decode"),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! # Gateway ratelimiting
+//! Discord closes the connection with [`CloseCode::Ratelimited`]
+//! when more than 120 payloads are sent in 60 seconds, so every
+//! payload we send goes through a [`SendRatelimiter`] first.
+//!
+//! [`CloseCode::Ratelimited`]: super::opcodes::CloseCode::Ratelimited
+use tokio::time::{Duration, Instant};
+
+/// How many payloads Discord allows to be sent every 60 seconds.
+pub const PAYLOADS_PER_MINUTE: u32 = 120;
+
+/// A token bucket for the payloads sent over one connection.
+///
+/// It holds half of [`PAYLOADS_PER_MINUTE`] tokens and refills
+/// the other half over the minute, so that no window of 60 seconds
+/// can contain more payloads than allowed.
+///
+/// Some of the tokens are reserved for heartbeats and identifying,
+/// which can never be delayed, so that commands sent by the user
+/// can't use them up.
+pub struct SendRatelimiter {
+    tokens: f64,
+    reserved: f64,
+    last_refill: Instant,
+}
+
+impl Default for SendRatelimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SendRatelimiter {
+    /// A full bucket for a new connection, only reserving a
+    /// token for identifying until the heartbeat interval is known.
+    pub fn new() -> Self {
+        Self {
+            tokens: Self::capacity(),
+            reserved: 1.0,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn capacity() -> f64 {
+        f64::from(PAYLOADS_PER_MINUTE / 2)
+    }
+
+    /// Tokens refilled every second.
+    fn refill_rate() -> f64 {
+        Self::capacity() / 60.0
+    }
+
+    /// Reserve enough tokens for a minute of heartbeats
+    /// sent every `heartbeat_interval` milliseconds, on top
+    /// of the one for identifying or resuming.
+    pub fn reserve_for_heartbeats(&mut self, heartbeat_interval: u64) {
+        let heartbeats = (60_000.0 / heartbeat_interval.max(1) as f64).ceil();
+        self.reserved = (heartbeats + 1.0).min(Self::capacity() - 1.0);
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * Self::refill_rate()).min(Self::capacity());
+        self.last_refill = now;
+    }
+
+    /// Whether a command can be sent right now
+    /// without using up the reserved tokens.
+    pub fn can_send(&mut self) -> bool {
+        self.refill();
+        self.tokens - self.reserved >= 1.0
+    }
+
+    /// How long until [`can_send`](#method.can_send) returns `true`.
+    pub fn available_in(&mut self) -> Duration {
+        self.refill();
+        let missing = (self.reserved + 1.0 - self.tokens).max(0.0);
+        Duration::from_secs_f64(missing / Self::refill_rate())
+    }
+
+    /// Take a token for a command, which must
+    /// only be done if [`can_send`](#method.can_send).
+    pub fn take(&mut self) {
+        self.tokens -= 1.0;
+    }
+
+    /// Take a token for a payload that has to be sent right away,
+    /// such as a heartbeat, using up the reserved tokens if needed.
+    pub fn take_reserved(&mut self) {
+        self.refill();
+        self.tokens = (self.tokens - 1.0).max(0.0);
+    }
+}
@@ -0,0 +1,280 @@
+//! # Sharding
+//! Once a bot is in enough guilds (2500 at most), Discord
+//! requires it to split its guilds between multiple gateway
+//! connections, called shards, each one receiving the events
+//! of the guilds for which `(guild_id >> 22) % shard_count == shard_id`.
+//!
+//! The [`ShardManager`] takes care of starting and supervising
+//! all of them, merging their events into a single receiver.
+use super::builder::IdentifyObject;
+#[cfg(feature = "cache")]
+use super::cache::Caches;
+use super::client::Client;
+use super::handle::ShardHandle;
+use super::payloads::DiscordEvent;
+use super::identify::{IdentifyQueue, LocalIdentifyQueue};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use crate::types::Snowflake;
+use crate::types::error::DerustError;
+use crate::http::{Http, Request, routing::Route};
+use reqwest::Method;
+use tokio::sync::{mpsc, mpsc::{Receiver, Sender}};
+use tokio::time::{delay_for, Duration};
+use tracing::{error, warn};
+use serde::Deserialize;
+
+/// Seconds to wait before restarting a shard that died.
+const RESTART_DELAY: u64 = 5;
+
+/// The response of the `/gateway/bot` endpoint.
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct GatewayBot {
+    pub url: String,
+    /// The recommended amount of shards to start.
+    pub shards: u16,
+    pub session_start_limit: SessionStartLimit,
+}
+
+/// How many more sessions can be started today, since
+/// Discord only allows a limited amount of identifies a day.
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct SessionStartLimit {
+    pub total: u32,
+    pub remaining: u32,
+    /// Milliseconds until `remaining` gets reset to `total`.
+    pub reset_after: u64,
+    /// How many shards are allowed to identify at the same time,
+    /// see [`IdentifyQueue`].
+    ///
+    /// [`IdentifyQueue`]: super::identify::IdentifyQueue
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: u16,
+}
+
+fn default_max_concurrency() -> u16 {
+    1
+}
+
+impl GatewayBot {
+    /// Request the gateway URL and the recommended
+    /// shard count for the bot `http` is authenticated as.
+    pub async fn get(http: &Http) -> Result<Self, DerustError> {
+        let route = Route::new(Method::GET, String::from("/gateway/bot"));
+        match http.request(Request::new(route)).await {
+            Err(DerustError::ApiError { status: 401, .. }) => Err(DerustError::InvalidToken),
+            r => r,
+        }
+    }
+}
+
+/// An event along with the ID of the shard that received it.
+pub type ShardEvent = (u16, Result<DiscordEvent, DerustError>);
+
+/// The handles of the running shards, by shard ID.
+type ShardHandles = Arc<Mutex<HashMap<u16, ShardHandle>>>;
+
+/// What every shard is started with.
+#[derive(Clone)]
+struct Shared {
+    #[cfg(feature = "cache")]
+    caches: Caches,
+    url: String,
+    http: Http,
+    queue: Arc<dyn IdentifyQueue>,
+    handles: ShardHandles,
+}
+
+/// The ID of the shard receiving the events of the
+/// guild `guild_id` when there are `shard_count` shards.
+pub fn shard_id(guild_id: Snowflake, shard_count: u16) -> u16 {
+    ((guild_id.0 >> 22) % u64::from(shard_count.max(1))) as u16
+}
+
+/// Starts one [`Client`] per shard and restarts the ones that die,
+/// unless the gateway closed them with a fatal [`CloseCode`], or
+/// invalidated their session while `auto_reidentify` is disabled.
+///
+/// ```rust,no_run
+/// use derust::types::gateway::builder::IdentifyObject;
+/// use derust::types::gateway::cache::Caches;
+/// use derust::types::gateway::shard::ShardManager;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let io = IdentifyObject::new(String::from("token"));
+///     let mut manager = ShardManager::new(io, Caches::initialize().await);
+///     let mut events = manager.start().await
+///         .expect("Failed to start the shards");
+///
+///     while let Some((shard_id, event)) = events.recv().await {
+///         println!("Shard {} received {:?}", shard_id, event);
+///         // commands are sent with the handle of a shard.
+///         let _handle = manager.handle(shard_id);
+///     }
+/// }
+/// ```
+///
+/// [`CloseCode`]: super::opcodes::CloseCode
+pub struct ShardManager {
+    identify_object: IdentifyObject,
+    #[cfg(feature = "cache")]
+    caches: Caches,
+    shard_count: Option<u16>,
+    shard_ids: Option<Range<u16>>,
+    identify_queue: Option<Arc<dyn IdentifyQueue>>,
+    /// The amount of shards the bot was started with.
+    total_shards: Option<u16>,
+    handles: ShardHandles,
+}
+
+impl ShardManager {
+    /// Construct a new `ShardManager`, which will identify every shard
+    /// with a copy of the provided [`IdentifyObject`], and share the caches
+    /// between all of them.
+    pub fn new(io: IdentifyObject, #[cfg(feature = "cache")] caches: Caches) -> Self {
+        Self {
+            identify_object: io,
+            #[cfg(feature = "cache")]
+            caches,
+            shard_count: None,
+            shard_ids: None,
+            identify_queue: None,
+            total_shards: None,
+            handles: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Start exactly `count` shards instead of the
+    /// amount recommended by Discord.
+    ///
+    /// Default: the `shards` returned by `/gateway/bot`
+    pub fn shard_count(&mut self, count: u16) -> &mut Self {
+        self.shard_count = Some(count);
+        self
+    }
+
+    /// Only start the shards with these IDs, for when the shards
+    /// are split between multiple processes. You will also want
+    /// to set both the [`shard_count`](#method.shard_count) and an
+    /// [`identify_queue`](#method.identify_queue) shared by all of them.
+    ///
+    /// Default: all the shards
+    pub fn shard_ids(&mut self, ids: Range<u16>) -> &mut Self {
+        self.shard_ids = Some(ids);
+        self
+    }
+
+    /// Set the queue every shard waits on before identifying.
+    ///
+    /// Default: a [`LocalIdentifyQueue`] with the `max_concurrency`
+    /// returned by `/gateway/bot`.
+    pub fn identify_queue(&mut self, queue: Arc<dyn IdentifyQueue>) -> &mut Self {
+        self.identify_queue = Some(queue);
+        self
+    }
+
+    /// Start all the shards, returning a tokio `mpsc` receiver over
+    /// which the events of every shard will be dispatched, tagged
+    /// with the ID of the shard that received them.
+    ///
+    /// Fails without starting anything if there aren't enough
+    /// session starts remaining to start every shard, or if the
+    /// shard IDs are out of range.
+    pub async fn start(&mut self) -> Result<Receiver<ShardEvent>, DerustError> {
+        let http = Http::new(&self.identify_object.token)?;
+        let gateway = GatewayBot::get(&http).await?;
+        let limit = &gateway.session_start_limit;
+        let shard_count = self.shard_count.unwrap_or(gateway.shards).max(1);
+        let shard_ids = self.shard_ids.clone().unwrap_or(0..shard_count);
+        if shard_ids.end > shard_count {
+            return Err(DerustError::InvalidInput(
+                format!("shards {:?} are out of range for {} shards", shard_ids, shard_count)
+            ));
+        }
+        if limit.remaining < shard_ids.len() as u32 {
+            return Err(DerustError::SessionStartLimit {
+                remaining: limit.remaining,
+                reset_after: limit.reset_after,
+            });
+        }
+        let queue = self.identify_queue.clone().unwrap_or_else(|| {
+            Arc::new(LocalIdentifyQueue::new(limit.max_concurrency))
+        });
+        let (tx, rx) = mpsc::channel(250);
+        self.total_shards = Some(shard_count);
+        let shared = Shared {
+            #[cfg(feature = "cache")]
+            caches: self.caches.clone(),
+            url: gateway.url,
+            http,
+            queue,
+            handles: self.handles.clone(),
+        };
+
+        // every shard uses the same client, so that the
+        // ratelimits of the token are tracked only once.
+        for shard_id in shard_ids {
+            let mut io = self.identify_object.clone();
+            io.shard(shard_id, shard_count)?;
+            tokio::spawn(supervise_shard(shard_id, io, shared.clone(), tx.clone()));
+        }
+
+        Ok(rx)
+    }
+
+    /// The handle of the shard `shard_id`, to send commands over its
+    /// connection, if it is running. Shards get a new handle every
+    /// time they are restarted, so it shouldn't be kept around.
+    pub fn handle(&self, shard_id: u16) -> Option<ShardHandle> {
+        self.handles.lock().expect("shard handles poisoned").get(&shard_id).cloned()
+    }
+
+    /// The handle of the shard receiving the events of the guild
+    /// `guild_id`, such as to request its members. `None` until
+    /// the shards are started.
+    pub fn guild_handle(&self, guild_id: Snowflake) -> Option<ShardHandle> {
+        self.handle(shard_id(guild_id, self.total_shards?))
+    }
+}
+
+/// Run a shard, forwarding its events to `tx`,
+/// and restart it every time it dies.
+async fn supervise_shard(
+    shard_id: u16,
+    io: IdentifyObject,
+    shared: Shared,
+    mut tx: Sender<ShardEvent>,
+) {
+    'shard: loop {
+        #[cfg(feature = "cache")]
+        let mut client = Client::with_http(
+            io.clone(), shared.caches.clone(), shared.url.clone(), shared.http.clone()
+        );
+        #[cfg(not(feature = "cache"))]
+        let mut client = Client::with_http(io.clone(), shared.url.clone(), shared.http.clone());
+        client.identify_queue(shared.queue.clone());
+        let (handle, mut events) = client.login().await;
+        shared.handles.lock().expect("shard handles poisoned").insert(shard_id, handle);
+        let mut fatal = false;
+        while let Some(event) = events.recv().await {
+            match &event {
+                Err(DerustError::GatewayClosed(code)) => fatal = code.is_fatal(),
+                Err(DerustError::SessionInvalidated) => fatal = true,
+                _ => {},
+            }
+            if tx.send((shard_id, event)).await.is_err() {
+                // nobody is listening anymore.
+                break 'shard;
+            }
+        }
+        if fatal {
+            error!("Shard {} can't reconnect, not restarting it.", shard_id);
+            break;
+        }
+        warn!("Shard {} died, restarting it.", shard_id);
+        delay_for(Duration::from_secs(RESTART_DELAY)).await;
+    }
+    shared.handles.lock().expect("shard handles poisoned").remove(&shard_id);
+}
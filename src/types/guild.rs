@@ -1,27 +1,36 @@
 use super::user::User;
 use serde::{Serialize, Deserialize};
+use serde_repr::{Serialize_repr, Deserialize_repr};
 use super::role::Role;
 use super::message::Emoji;
 use super::voice::VoiceState;
 use super::channel::Channel;
 use super::gateway::activity::PresenceUpdate;
-use super::permission::PermissionOverwrite;
+use super::permission::{PermissionOverwrite, Permissions, permissions_string};
+use enumflags2::BitFlags;
 use crate::types::{Snowflake, Timestamp};
 use super::CachedTypes;
+use super::error::DerustError;
+use crate::http::Http;
+use crate::http::guild::{AuditLogFilter, EditRole, ModifyGuild, ModifyMember};
+use crate::http::webhook::WebhookClient;
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct Guild {
     pub id: Snowflake,
     pub name: String,
-    pub icon: String,
-    pub splash: String,
-    pub discovery_splash: String,
+    pub icon: Option<String>,
+    pub splash: Option<String>,
+    pub discovery_splash: Option<String>,
     #[serde(default)]
     pub owner: bool,
     pub owner_id: Snowflake,
-    pub permissions: Option<i32>, //todo: replace with proper permissions
+    /// The permissions of the current user in the guild,
+    /// only set when getting the guilds they are in.
+    #[serde(default, with = "permissions_string::option")]
+    pub permissions: Option<BitFlags<Permissions>>,
     pub region: String,
-    pub afk_channel_id: Snowflake,
+    pub afk_channel_id: Option<Snowflake>,
     pub afk_timeout: i32,
     #[serde(default)]
     pub embed_enabled: bool,
@@ -34,13 +43,13 @@ pub struct Guild {
     /// See https://discord.com/developers/docs/resources/guild#guild-object-guild-features
     pub features: Vec<String>,
     pub mfa_level: MFALevel,
-    pub application_id: Snowflake,
+    pub application_id: Option<Snowflake>,
     #[serde(default)]
     pub widget_enabled: bool,
     pub widget_channel_id: Option<Snowflake>,
-    pub system_channel_id: Snowflake,
+    pub system_channel_id: Option<Snowflake>,
     pub system_channel_flags: i32,
-    pub rules_channel_id: Snowflake,
+    pub rules_channel_id: Option<Snowflake>,
     pub joined_at: Option<Timestamp>, // utc time
     #[serde(default)]
     pub large: bool,
@@ -53,13 +62,13 @@ pub struct Guild {
     pub presences: Option<Vec<PresenceUpdate>>,
     pub max_presences: Option<i32>,
     pub max_members: Option<i32>,
-    pub vanity_url_code: String,
-    pub description: String,
-    pub banner: String,
+    pub vanity_url_code: Option<String>,
+    pub description: Option<String>,
+    pub banner: Option<String>,
     pub premium_tier: i32,
     pub premium_subscription_count: Option<i32>,
     pub preferred_locale: String,
-    pub public_updates_channel_id: Snowflake,
+    pub public_updates_channel_id: Option<Snowflake>,
     pub max_video_channel_users: Option<i32>,
     pub approximate_member_count: Option<i32>,
     pub approximate_presence_count: Option<i32>,
@@ -67,7 +76,75 @@ pub struct Guild {
 
 impl CachedTypes for Guild {}
 
-#[derive(Deserialize, Serialize, Clone, Debug, Eq, PartialEq)]
+impl Guild {
+    pub async fn modify(
+        &self,
+        http: &Http,
+        guild: &ModifyGuild,
+        reason: Option<&str>,
+    ) -> Result<Guild, DerustError> {
+        http.modify_guild(self.id, guild, reason).await
+    }
+
+    pub async fn member(&self, http: &Http, user_id: Snowflake) -> Result<GuildMember, DerustError> {
+        http.get_member(self.id, user_id).await
+    }
+
+    pub async fn modify_member(
+        &self,
+        http: &Http,
+        user_id: Snowflake,
+        member: &ModifyMember,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        http.modify_member(self.id, user_id, member, reason).await
+    }
+
+    pub async fn kick(
+        &self,
+        http: &Http,
+        user_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        http.kick_member(self.id, user_id, reason).await
+    }
+
+    /// See [`Http::create_ban`].
+    pub async fn ban(
+        &self,
+        http: &Http,
+        user_id: Snowflake,
+        delete_message_days: Option<u8>,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        http.create_ban(self.id, user_id, delete_message_days, reason).await
+    }
+
+    pub async fn unban(
+        &self,
+        http: &Http,
+        user_id: Snowflake,
+        reason: Option<&str>,
+    ) -> Result<(), DerustError> {
+        http.remove_ban(self.id, user_id, reason).await
+    }
+
+    pub async fn create_role(
+        &self,
+        http: &Http,
+        role: &EditRole,
+        reason: Option<&str>,
+    ) -> Result<Role, DerustError> {
+        http.create_role(self.id, role, reason).await
+    }
+
+    pub async fn audit_log(&self, http: &Http, filter: &AuditLogFilter) -> Result<AuditLog, DerustError> {
+        http.get_audit_log(self.id, filter).await
+    }
+}
+
+#[derive(Serialize_repr, Deserialize_repr, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub enum VerificationLevel {
     None = 0,
     Low = 1,
@@ -76,14 +153,16 @@ pub enum VerificationLevel {
     VeryHigh = 4,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub enum ExplicitFilterLevel {
     Disabled = 0,
     MembersWithoutRoles = 1,
     AllMembers = 2,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub enum MFALevel {
     Disabled = 0,
     Elevated = 1,
@@ -91,7 +170,8 @@ pub enum MFALevel {
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct GuildMember {
-    pub user: User,
+    /// Not included in the member object attached to messages.
+    pub user: Option<User>,
     pub nick: Option<String>,
     pub roles: Vec<Snowflake>,
     pub joined_at: Option<Timestamp>,
@@ -110,19 +190,28 @@ pub struct AuditLog {
     pub integrations: Vec<Integration>,
 }
 
-#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct Webhook {
     pub id: Snowflake,
     pub r#type: WebhookType,
     pub guild_id: Option<Snowflake>,
     pub channel_id: Snowflake,
     pub user: Option<User>,
-    pub name: String,
-    pub avatar: String,
+    pub name: Option<String>,
+    pub avatar: Option<String>,
+    /// Only set for incoming webhooks.
     pub token: Option<String>,
 }
 
-#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+impl Webhook {
+    /// A client to execute this webhook with, if it has a token.
+    pub fn client(&self) -> Option<Result<WebhookClient, DerustError>> {
+        self.token.clone().map(|token| WebhookClient::new(self.id, token))
+    }
+}
+
+#[derive(Serialize_repr, Deserialize_repr, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub enum WebhookType {
     Incoming = 1,
     ChannelFollower = 2,
@@ -134,17 +223,19 @@ pub struct Integration {
     pub name: String,
     pub r#type: String,
     pub enabled: bool,
-    pub syncing: bool,
-    pub role_id: String,
-    pub enable_emoticons: Option<String>,
-    pub expire_behavior: IntegrationExpireBehavior,
-    pub expire_grace_period: i32,
-    pub user: User,
+    /// The fields below are not included for bot integrations.
+    pub syncing: Option<bool>,
+    pub role_id: Option<Snowflake>,
+    pub enable_emoticons: Option<bool>,
+    pub expire_behavior: Option<IntegrationExpireBehavior>,
+    pub expire_grace_period: Option<i32>,
+    pub user: Option<User>,
     pub account: IntegrationAccount,
-    pub synced_at: Timestamp,
+    pub synced_at: Option<Timestamp>,
 }
 
-#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+#[derive(Deserialize_repr, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub enum IntegrationExpireBehavior {
     RemoveRole = 0,
     Kick = 1,
@@ -158,7 +249,7 @@ pub struct IntegrationAccount {
 
 #[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct AuditLogEntry {
-    pub target_id: Snowflake,
+    pub target_id: Option<Snowflake>,
     pub changes: Option<Vec<AuditLogChange>>,
     pub user_id: Snowflake,
     pub id: Snowflake,
@@ -167,7 +258,8 @@ pub struct AuditLogEntry {
     pub reason: Option<String>,
 }
 
-#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+#[derive(Deserialize_repr, Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub enum AuditLogEvent {
     GuildUpdate = 1,
     ChannelCreate = 10,
@@ -208,14 +300,15 @@ pub enum AuditLogEvent {
 
 #[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct OptionalAuditLogEntry {
-    pub delete_member_days: String,
-    pub members_removed: String,
-    pub channel_id: Snowflake,
-    pub message_id: Snowflake,
-    pub count: String,
-    pub id: Snowflake,
-    pub r#type: String,
-    pub role_name: String,
+    /// Only the fields relevant to the action are set.
+    pub delete_member_days: Option<String>,
+    pub members_removed: Option<String>,
+    pub channel_id: Option<Snowflake>,
+    pub message_id: Option<Snowflake>,
+    pub count: Option<String>,
+    pub id: Option<Snowflake>,
+    pub r#type: Option<String>,
+    pub role_name: Option<String>,
 }
 
 #[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -235,10 +328,22 @@ pub enum MixedType {
     RoleVec(Option<Vec<Role>>),
     Bool(bool),
     OverwriteVec(Option<Vec<PermissionOverwrite>>),
+    /// Any value that isn't one of the above, so that
+    /// new kinds of changes don't break the audit log.
+    Other(serde_json::Value),
+}
+
+/// A ban of a user from a guild.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct Ban {
+    pub reason: Option<String>,
+    pub user: User,
 }
 
-#[derive(Serialize, Deserialize, Eq, PartialEq, Debug)] // serialize required because of enum in gateway/payloads.rs
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)] // serialize required because of enum in gateway/payloads.rs
 pub struct PartialGuild {
     pub id: Snowflake,
+    /// Not set when the user was removed from the guild.
+    #[serde(default)]
     pub unavailable: bool,
 }
\ No newline at end of file
@@ -1,22 +1,27 @@
-use super::guild::Guild;
+use super::guild::PartialGuild;
 use super::channel::Channel;
 use super::user::User;
 use serde::{Deserialize};
-use crate::types::Snowflake;
+use serde_repr::Deserialize_repr;
+use crate::types::Timestamp;
 
 #[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct Invite {
     pub code: String,
-    pub guild: Option<Guild>,
+    pub guild: Option<PartialGuild>,
     pub channel: Channel,
     pub inviter: Option<User>,
     pub target_user: Option<User>,
     pub target_user_type: Option<TargetUserType>,
     pub approximate_presence_count: Option<i32>,
     pub approximate_member_count: Option<i32>,
+    /// Only included when listing the invites of a guild or channel.
+    #[serde(flatten)]
+    pub metadata: Option<InviteMetadata>,
 }
 
-#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+#[derive(Deserialize_repr, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub enum TargetUserType {
     Stream = 1,
 }
@@ -27,5 +32,5 @@ pub struct InviteMetadata {
     pub max_uses: i32,
     pub max_age: i32,
     pub temporary: bool,
-    pub created_at: Snowflake,
+    pub created_at: Timestamp,
 }
\ No newline at end of file
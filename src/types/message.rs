@@ -2,21 +2,24 @@ use super::user::User;
 use super::guild::GuildMember;
 use super::channel::ChannelMention;
 use serde::{Serialize, Deserialize};
-use super::role::Role;
+use serde_repr::{Serialize_repr, Deserialize_repr};
 use crate::types::{Snowflake, Timestamp};
+use super::error::DerustError;
+use crate::http::Http;
+use crate::http::channel::{CreateMessage, EditMessage};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct MentionedUsers {
     pub id: Snowflake,
     pub username: String,
     pub discriminator: String,
-    pub avatar: String,
-    pub verified: bool,
-    pub email: String,
-    pub flags: i64,
-    pub premium_type: i64,
-    pub public_flags: i64,
-    pub member: GuildMember,
+    pub avatar: Option<String>,
+    #[serde(default)]
+    pub bot: bool,
+    pub public_flags: Option<i64>,
+    /// Only included in guild messages.
+    pub member: Option<GuildMember>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -25,7 +28,7 @@ pub struct Message {
     pub channel_id: Snowflake,
     pub guild_id: Option<Snowflake>,
     pub author: User,
-    pub member: GuildMember,
+    pub member: Option<GuildMember>,
     pub content: String,
     pub timestamp: Timestamp,
     pub edited_timestamp: Option<Timestamp>,
@@ -36,6 +39,7 @@ pub struct Message {
     pub mention_channels: Option<Vec<ChannelMention>>,
     pub attachments: Vec<Attachment>,
     pub embeds: Vec<Embed>,
+    #[serde(default)]
     pub reactions: Vec<MessageReaction>,
     pub nonce: Option<String>,
     pub pinned: bool,
@@ -47,6 +51,46 @@ pub struct Message {
     pub flags: Option<i32>,
 }
 
+impl Message {
+    /// Send a message in the same channel, replying to this one.
+    pub async fn reply(&self, http: &Http, content: String) -> Result<Message, DerustError> {
+        http.create_message(self.channel_id, &CreateMessage {
+            message_reference: Some(MessageReference {
+                message_id: Some(self.id),
+                channel_id: self.channel_id,
+                guild_id: self.guild_id,
+            }),
+            ..CreateMessage::new(content)
+        }).await
+    }
+
+    pub async fn edit(&self, http: &Http, message: &EditMessage) -> Result<Message, DerustError> {
+        http.edit_message(self.channel_id, self.id, message).await
+    }
+
+    pub async fn delete(&self, http: &Http, reason: Option<&str>) -> Result<(), DerustError> {
+        http.delete_message(self.channel_id, self.id, reason).await
+    }
+
+    pub async fn pin(&self, http: &Http, reason: Option<&str>) -> Result<(), DerustError> {
+        http.pin_message(self.channel_id, self.id, reason).await
+    }
+
+    pub async fn unpin(&self, http: &Http, reason: Option<&str>) -> Result<(), DerustError> {
+        http.unpin_message(self.channel_id, self.id, reason).await
+    }
+
+    /// React to this message as the bot.
+    pub async fn react(&self, http: &Http, emoji: &ReactionType) -> Result<(), DerustError> {
+        http.create_reaction(self.channel_id, self.id, emoji).await
+    }
+
+    /// Remove the reactions of everyone on this message.
+    pub async fn delete_reactions(&self, http: &Http, reason: Option<&str>) -> Result<(), DerustError> {
+        http.delete_all_reactions(self.channel_id, self.id, reason).await
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct MessageReaction {
     pub count: i32,
@@ -60,7 +104,8 @@ pub struct MessageActivity {
     pub party_id: Option<Snowflake>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub enum MessageActivityType {
     Join = 1,
     Spectate = 2,
@@ -68,7 +113,8 @@ pub enum MessageActivityType {
     JoinRequest = 5
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[derive(Serialize_repr, Deserialize_repr, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub enum MessageType {
     Default = 0,
     AddRecipient = 1,
@@ -98,12 +144,17 @@ pub struct MessageApplication {
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct MessageReference {
-    pub message_id: Snowflake,
+    pub message_id: Option<Snowflake>,
     pub channel_id: Snowflake,
-    pub guild_id: Snowflake,
+    pub guild_id: Option<Snowflake>,
 }
 
+/// Which mentions in the content of a message
+/// actually notify the mentioned users.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
 pub struct AllowedMentions {
+    /// Any of `roles`, `users` and `everyone`, to allow
+    /// every mention of that kind.
     pub parse: Vec<String>,
     pub roles: Vec<Snowflake>,
     pub users: Vec<Snowflake>,
@@ -116,8 +167,8 @@ pub struct Attachment {
     pub size: i32,
     pub url: String,
     pub proxy_url: String,
-    pub height: i32,
-    pub width: i32,
+    pub height: Option<i32>,
+    pub width: Option<i32>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -128,12 +179,13 @@ pub struct Embed {
     pub url: Option<String>,
     pub timestamp: Option<Timestamp>,
     pub color: Option<i32>,
-    pub footer: Option<Vec<EmbedFooter>>,
+    pub footer: Option<EmbedFooter>,
     pub image: Option<EmbedImage>,
     pub thumbnail: Option<EmbedThumbnail>,
     pub video: Option<EmbedVideo>,
     pub provider: Option<EmbedProvider>,
     pub author: Option<EmbedAuthor>,
+    #[serde(default)]
     pub fields: Vec<EmbedField>,
 }
 
@@ -191,9 +243,10 @@ pub struct EmbedVideo {
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct Emoji {
-    pub id: Snowflake,
+    /// `None` for unicode emojis.
+    pub id: Option<Snowflake>,
     pub name: String,
-    pub roles: Option<Role>,
+    pub roles: Option<Vec<Snowflake>>,
     pub user: Option<User>,
     #[serde(default)]
     pub require_colons: bool,
@@ -203,4 +256,47 @@ pub struct Emoji {
     pub animated: bool,
     #[serde(default)]
     pub available: bool,
-}
\ No newline at end of file
+}
+/// An emoji to react to a message with.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReactionType {
+    /// A unicode emoji, such as `"👍"`.
+    Unicode(String),
+    /// A custom emoji of a guild.
+    Custom {
+        name: String,
+        id: Snowflake,
+    },
+}
+
+impl ReactionType {
+    /// The emoji as it has to be put in the path of the
+    /// reaction endpoints, that is percent-encoded for unicode
+    /// emojis and `name:id` for custom ones.
+    pub fn to_path_segment(&self) -> String {
+        match self {
+            ReactionType::Unicode(emoji) => utf8_percent_encode(emoji, NON_ALPHANUMERIC).to_string(),
+            ReactionType::Custom { name, id } => {
+                format!("{}:{}", utf8_percent_encode(name, NON_ALPHANUMERIC), id)
+            },
+        }
+    }
+}
+
+impl From<&Emoji> for ReactionType {
+    fn from(emoji: &Emoji) -> Self {
+        match emoji.id {
+            Some(id) => ReactionType::Custom { name: emoji.name.clone(), id },
+            None => ReactionType::Unicode(emoji.name.clone()),
+        }
+    }
+}
+
+impl From<Emoji> for ReactionType {
+    fn from(emoji: Emoji) -> Self {
+        match emoji.id {
+            Some(id) => ReactionType::Custom { name: emoji.name, id },
+            None => ReactionType::Unicode(emoji.name),
+        }
+    }
+}
@@ -16,6 +16,7 @@
 //! to initialize a connection to the gateway.
 use serde::{Serialize, Deserialize};
 use chrono::{DateTime, Utc};
+use std::fmt;
 
 pub mod error;
 pub mod invite;
@@ -34,6 +35,25 @@ pub mod guild;
 /// Try to use it whenever possible.
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash, Copy)]
 pub struct Snowflake(#[serde(with = "type_string")] pub u64);
+
+/// The first millisecond of 2015, from which
+/// the timestamps of Snowflakes are counted.
+const DISCORD_EPOCH: u64 = 1_420_070_400_000;
+
+impl Snowflake {
+    /// When the object with this ID was created,
+    /// in milliseconds since the UNIX epoch.
+    pub fn timestamp(&self) -> u64 {
+        (self.0 >> 22) + DISCORD_EPOCH
+    }
+}
+
+impl fmt::Display for Snowflake {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// Automatic conversion from String into a DateTime struct,
 /// because the API returns time as a String formatted as
 /// per the [ISO8601 standard](https://en.wikipedia.org/wiki/ISO_8601) for both date and time.
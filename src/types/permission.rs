@@ -1,17 +1,38 @@
 use serde::{Serialize, Deserialize};
+use enumflags2::BitFlags;
 use crate::types::Snowflake;
+use super::channel::Channel;
+use super::guild::{Guild, GuildMember};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct PermissionOverwrite {
     pub id: Snowflake,
     pub r#type: String,
-    pub allow: i32,
-    pub deny: i32,
+    #[serde(with = "permissions_string")]
+    pub allow: BitFlags<Permissions>,
+    #[serde(with = "permissions_string")]
+    pub deny: BitFlags<Permissions>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+/// For a definition of all the enum values, see
+/// the [developer documentation](https://discord.com/developers/docs/topics/permissions#permissions-bitwise-permission-flags).
+///
+/// Combine them with [`BitFlags`], like the [`Intents`]:
+/// ```rust
+/// use derust::types::permission::Permissions;
+/// use enumflags2::BitFlags;
+///
+/// let permissions = Permissions::SendMessages | Permissions::EmbedLinks;
+/// assert!(permissions.contains(Permissions::SendMessages));
+/// assert!(!permissions.contains(Permissions::SendMessages | Permissions::AttachFiles));
+/// assert_eq!(BitFlags::<Permissions>::from_bits_truncate(1 << 11), Permissions::SendMessages);
+/// ```
+///
+/// [`Intents`]: crate::types::gateway::builder::Intents
+#[derive(BitFlags, Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u64)]
 pub enum Permissions {
-    SendMessage = 1 << 0,
+    CreateInstantInvite = 1 << 0,
     KickMembers = 1 << 1,
     BanMembers = 1 << 2,
     Administrator = 1 << 3,
@@ -42,4 +63,314 @@ pub enum Permissions {
     ManageRoles = 1 << 28,
     ManageWebhooks = 1 << 29,
     ManageEmojis = 1 << 30,
-}
\ No newline at end of file
+    UseSlashCommands = 1 << 31,
+    RequestToSpeak = 1 << 32,
+}
+
+/// The permissions that are implicitly denied
+/// along with [`Permissions::SendMessages`].
+fn send_messages_dependent() -> BitFlags<Permissions> {
+    Permissions::SendTTSMessages | Permissions::MentionEveryone
+        | Permissions::EmbedLinks | Permissions::AttachFiles
+}
+
+/// The permissions of `member` in `guild` regardless of the channel,
+/// from the `@everyone` role and the roles of the member.
+///
+/// `member.user` needs to be set to know if the member owns the guild,
+/// which it is when members are received from the API or in events
+/// other than [`MessageCreate`].
+///
+/// [`MessageCreate`]: crate::types::gateway::payloads::DiscordEvent::MessageCreate
+pub fn guild_permissions(guild: &Guild, member: &GuildMember) -> BitFlags<Permissions> {
+    if member.user.as_ref().map(|u| u.id) == Some(guild.owner_id) {
+        return BitFlags::all();
+    }
+    // the ID of `@everyone` is the ID of the guild.
+    let permissions = guild.roles.iter()
+        .filter(|r| r.id == guild.id || member.roles.contains(&r.id))
+        .fold(BitFlags::empty(), |permissions, r| permissions | r.permissions);
+    if permissions.contains(Permissions::Administrator) {
+        return BitFlags::all();
+    }
+    permissions
+}
+
+/// The permissions of `member` in `channel` of `guild`, which are
+/// the ones of [`guild_permissions`] with the overwrites of the
+/// channel applied to them.
+///
+/// The overwrites are applied in this order, each one taking over
+/// the previous ones: `@everyone`, then the roles of the member
+/// together, then the member.
+///
+/// Without [`Permissions::ViewChannel`] every permission is denied,
+/// and without [`Permissions::SendMessages`] the ones to mention everyone,
+/// send TTS messages, embed links and attach files are as well.
+pub fn channel_permissions(
+    guild: &Guild,
+    member: &GuildMember,
+    channel: &Channel,
+) -> BitFlags<Permissions> {
+    let mut permissions = guild_permissions(guild, member);
+    // the owner has every permission, Administrator included.
+    if permissions.contains(Permissions::Administrator) {
+        return BitFlags::all();
+    }
+    let overwrites = channel.permission_overwrites.as_deref().unwrap_or(&[]);
+    if let Some(everyone) = overwrites.iter().find(|o| o.id == guild.id) {
+        permissions = (permissions & !everyone.deny) | everyone.allow;
+    }
+    let (allow, deny) = overwrites.iter()
+        .filter(|o| o.r#type == "role" && o.id != guild.id && member.roles.contains(&o.id))
+        .fold((BitFlags::empty(), BitFlags::empty()), |(allow, deny), o| {
+            (allow | o.allow, deny | o.deny)
+        });
+    permissions = (permissions & !deny) | allow;
+    let user_id = member.user.as_ref().map(|u| u.id);
+    if let Some(own) = overwrites.iter().find(|o| o.r#type == "member" && Some(o.id) == user_id) {
+        permissions = (permissions & !own.deny) | own.allow;
+    }
+
+    if !permissions.contains(Permissions::ViewChannel) {
+        return BitFlags::empty();
+    }
+    if !permissions.contains(Permissions::SendMessages) {
+        permissions &= !send_messages_dependent();
+    }
+    permissions
+}
+
+/// (De)serialization of permissions, which the API sends either
+/// as an integer or as a string so that every bit fits in them.
+///
+/// They are serialized as a string, and bits unknown to
+/// [`Permissions`] are left out when deserializing them.
+pub(crate) mod permissions_string {
+    use std::fmt;
+    use enumflags2::BitFlags;
+    use serde::{de::{self, Deserializer, Visitor}, ser::Serializer};
+    use super::Permissions;
+
+    pub fn serialize<S: Serializer>(value: &BitFlags<Permissions>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&value.bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BitFlags<Permissions>, D::Error> {
+        deserializer.deserialize_any(PermissionsVisitor)
+    }
+
+    struct PermissionsVisitor;
+
+    impl<'de> Visitor<'de> for PermissionsVisitor {
+        type Value = BitFlags<Permissions>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("permissions as an integer or a string")
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+            Ok(BitFlags::from_bits_truncate(v))
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+            Ok(BitFlags::from_bits_truncate(v as u64))
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            v.parse().map(BitFlags::from_bits_truncate).map_err(E::custom)
+        }
+    }
+
+    /// The same, for the permissions which aren't always sent.
+    pub mod option {
+        use enumflags2::BitFlags;
+        use serde::{Deserialize, Deserializer, Serializer};
+        use super::super::Permissions;
+
+        pub fn serialize<S: Serializer>(
+            value: &Option<BitFlags<Permissions>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => super::serialize(value, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<BitFlags<Permissions>>, D::Error> {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] BitFlags<Permissions>);
+            Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|w| w.0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use enumflags2::BitFlags;
+    use serde_json::json;
+    use crate::types::Snowflake;
+    use crate::types::channel::Channel;
+    use crate::types::guild::{Guild, GuildMember};
+    use crate::types::role::Role;
+    use super::*;
+
+    const GUILD_ID: u64 = 1;
+    const OWNER_ID: u64 = 2;
+    const USER_ID: u64 = 3;
+    const MODERATOR: u64 = 10;
+    const MUTED: u64 = 11;
+    const ADMIN: u64 = 12;
+
+    fn role(id: u64, permissions: BitFlags<Permissions>) -> Role {
+        Role {
+            id: Snowflake(id),
+            name: id.to_string(),
+            color: 0,
+            hoist: false,
+            position: 0,
+            permissions,
+            managed: false,
+            mentionable: false,
+        }
+    }
+
+    fn guild() -> Guild {
+        let mut guild: Guild = serde_json::from_value(json!({
+            "id": GUILD_ID.to_string(), "name": "guild", "icon": null, "splash": null,
+            "discovery_splash": null, "owner_id": OWNER_ID.to_string(), "region": "europe",
+            "afk_channel_id": null, "afk_timeout": 300, "embed_channel_id": null,
+            "verification_level": 0, "default_message_notifications": 0,
+            "explicit_content_filter": 0, "roles": [], "emojis": [], "features": [],
+            "mfa_level": 0, "application_id": null, "widget_channel_id": null,
+            "system_channel_id": null, "system_channel_flags": 0, "rules_channel_id": null,
+            "joined_at": null, "vanity_url_code": null, "description": null, "banner": null,
+            "premium_tier": 0, "preferred_locale": "en-US", "public_updates_channel_id": null,
+        })).expect("invalid guild");
+        guild.roles = vec![
+            role(GUILD_ID, Permissions::ViewChannel | Permissions::SendMessages
+                | Permissions::EmbedLinks | Permissions::AddReactions),
+            role(MODERATOR, Permissions::KickMembers | Permissions::ManageMessages),
+            role(MUTED, BitFlags::empty()),
+            role(ADMIN, Permissions::Administrator.into()),
+        ];
+        guild
+    }
+
+    fn member(user_id: u64, roles: &[u64]) -> GuildMember {
+        let mut member: GuildMember = serde_json::from_value(json!({
+            "user": { "id": user_id.to_string(), "username": "user", "discriminator": "0001", "avatar": null },
+            "nick": null, "roles": [], "joined_at": null, "premium_since": null,
+            "deaf": false, "mute": false,
+        })).expect("invalid member");
+        member.roles = roles.iter().cloned().map(Snowflake).collect();
+        member
+    }
+
+    fn overwrite(
+        id: u64,
+        r#type: &str,
+        allow: BitFlags<Permissions>,
+        deny: BitFlags<Permissions>,
+    ) -> PermissionOverwrite {
+        PermissionOverwrite { id: Snowflake(id), r#type: String::from(r#type), allow, deny }
+    }
+
+    fn channel(overwrites: Vec<PermissionOverwrite>) -> Channel {
+        let mut channel: Channel = serde_json::from_value(json!({
+            "id": "100", "type": 0, "guild_id": GUILD_ID.to_string(),
+        })).expect("invalid channel");
+        channel.permission_overwrites = Some(overwrites);
+        channel
+    }
+
+    #[test]
+    fn owner_has_every_permission() {
+        let hidden = channel(vec![
+            overwrite(GUILD_ID, "role", BitFlags::empty(), BitFlags::all()),
+        ]);
+        assert_eq!(guild_permissions(&guild(), &member(OWNER_ID, &[])), BitFlags::all());
+        assert_eq!(channel_permissions(&guild(), &member(OWNER_ID, &[]), &hidden), BitFlags::all());
+    }
+
+    #[test]
+    fn administrator_has_every_permission() {
+        let hidden = channel(vec![
+            overwrite(ADMIN, "role", BitFlags::empty(), BitFlags::all()),
+        ]);
+        assert_eq!(guild_permissions(&guild(), &member(USER_ID, &[ADMIN])), BitFlags::all());
+        assert_eq!(channel_permissions(&guild(), &member(USER_ID, &[ADMIN]), &hidden), BitFlags::all());
+    }
+
+    #[test]
+    fn guild_permissions_combine_everyone_and_roles() {
+        let everyone = Permissions::ViewChannel | Permissions::SendMessages
+            | Permissions::EmbedLinks | Permissions::AddReactions;
+        assert_eq!(guild_permissions(&guild(), &member(USER_ID, &[])), everyone);
+        assert_eq!(
+            guild_permissions(&guild(), &member(USER_ID, &[MODERATOR])),
+            everyone | Permissions::KickMembers | Permissions::ManageMessages,
+        );
+    }
+
+    #[test]
+    fn role_overwrites_apply_after_everyone() {
+        let channel = channel(vec![
+            overwrite(GUILD_ID, "role", BitFlags::empty(), Permissions::SendMessages.into()),
+            overwrite(MODERATOR, "role", Permissions::SendMessages.into(), BitFlags::empty()),
+        ]);
+        let user = channel_permissions(&guild(), &member(USER_ID, &[]), &channel);
+        assert!(!user.contains(Permissions::SendMessages));
+        let moderator = channel_permissions(&guild(), &member(USER_ID, &[MODERATOR]), &channel);
+        assert!(moderator.contains(Permissions::SendMessages));
+    }
+
+    #[test]
+    fn role_allows_apply_after_role_denies() {
+        let channel = channel(vec![
+            overwrite(MUTED, "role", BitFlags::empty(), Permissions::AddReactions.into()),
+            overwrite(MODERATOR, "role", Permissions::AddReactions.into(), BitFlags::empty()),
+        ]);
+        let muted = channel_permissions(&guild(), &member(USER_ID, &[MUTED]), &channel);
+        assert!(!muted.contains(Permissions::AddReactions));
+        let both = channel_permissions(&guild(), &member(USER_ID, &[MUTED, MODERATOR]), &channel);
+        assert!(both.contains(Permissions::AddReactions));
+    }
+
+    #[test]
+    fn member_overwrite_applies_last() {
+        let channel = channel(vec![
+            overwrite(MODERATOR, "role", Permissions::ManageChannels.into(), BitFlags::empty()),
+            overwrite(USER_ID, "member", Permissions::AttachFiles.into(), Permissions::ManageChannels.into()),
+        ]);
+        let permissions = channel_permissions(&guild(), &member(USER_ID, &[MODERATOR]), &channel);
+        assert!(!permissions.contains(Permissions::ManageChannels));
+        assert!(permissions.contains(Permissions::AttachFiles));
+    }
+
+    #[test]
+    fn missing_view_channel_denies_everything() {
+        let channel = channel(vec![
+            overwrite(GUILD_ID, "role", BitFlags::empty(), Permissions::ViewChannel.into()),
+        ]);
+        assert_eq!(channel_permissions(&guild(), &member(USER_ID, &[MODERATOR]), &channel), BitFlags::empty());
+    }
+
+    #[test]
+    fn missing_send_messages_denies_dependent_permissions() {
+        let channel = channel(vec![
+            overwrite(
+                GUILD_ID, "role",
+                Permissions::SendTTSMessages | Permissions::AttachFiles,
+                Permissions::SendMessages.into(),
+            ),
+        ]);
+        assert_eq!(
+            channel_permissions(&guild(), &member(USER_ID, &[]), &channel),
+            Permissions::ViewChannel | Permissions::AddReactions,
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
+use enumflags2::BitFlags;
 use crate::types::Snowflake;
+use super::permission::{Permissions, permissions_string};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct Role {
@@ -8,7 +10,8 @@ pub struct Role {
     pub color: i32,
     pub hoist: bool,
     pub position: i32,
-    pub permissions: i32,
+    #[serde(with = "permissions_string")]
+    pub permissions: BitFlags<Permissions>,
     pub managed: bool,
     pub mentionable: bool,
 }
\ No newline at end of file
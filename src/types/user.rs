@@ -1,7 +1,9 @@
 use serde::{Serialize, Deserialize};
+use serde_repr::{Serialize_repr, Deserialize_repr};
 use super::channel::Channel;
 use super::error::DerustError;
-use crate::API_URL;
+use crate::http::{Http, Request, routing::Route};
+use reqwest::Method;
 use super::channel::CreatePrivateChannelBody;
 use crate::types::Snowflake;
 use super::CachedTypes;
@@ -13,17 +15,32 @@ pub struct User {
     pub id: Snowflake,
     pub username: String,
     pub discriminator: String,
-    pub avatar: String,
-    pub verified: bool,
-    pub email: String,
-    pub flags: i64,
-    pub premium_type: PremiumType,
-    pub public_flags: i64,
+    pub avatar: Option<String>,
+    #[serde(default)]
+    pub bot: bool,
+    #[serde(default)]
+    pub system: bool,
+    pub verified: Option<bool>,
+    pub email: Option<String>,
+    pub flags: Option<i64>,
+    pub premium_type: Option<PremiumType>,
+    pub public_flags: Option<i64>,
 }
 
 impl CachedTypes for User {}
 
+/// A user object in which only the `id` is guaranteed
+/// to be present, such as the one in presence updates.
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct PartialUser {
+    pub id: Snowflake,
+    pub username: Option<String>,
+    pub discriminator: Option<String>,
+    pub avatar: Option<String>,
+}
+
+#[derive(Serialize_repr, Deserialize_repr, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub enum PremiumType {
     None = 0,
     NitroClassic = 1,
@@ -49,21 +66,10 @@ pub enum PublicFlags {
 }
 
 impl User {
-    pub async fn create_private_channel(&self) -> Result<Channel, DerustError> {
-        let resp = reqwest::Client::new()
-            .post(format!("{}/users/@me/channels", API_URL).as_str())
-            .json(
-                &CreatePrivateChannelBody{ recipient_id: self.clone().id }
-            )
-            //.headers()
-            .send().await;
-        match resp {
-            Ok(v) => {
-                Ok(v.json::<Channel>().await.unwrap())
-            },
-            Err(_) => {
-                Err(DerustError::UnknownError)
-            }
-        }
+    pub async fn create_private_channel(&self, http: &Http) -> Result<Channel, DerustError> {
+        let route = Route::new(Method::POST, String::from("/users/@me/channels"));
+        http.request(Request::new(route).json(
+            &CreatePrivateChannelBody{ recipient_id: self.id }
+        )?).await
     }
 }
\ No newline at end of file
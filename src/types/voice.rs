@@ -6,10 +6,10 @@ use super::CachedTypes;
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct VoiceState {
     pub guild_id: Option<Snowflake>,
-    pub channel_id: Snowflake,
+    pub channel_id: Option<Snowflake>,
     pub user_id: Snowflake,
     pub member: Option<GuildMember>,
-    pub session_id: Snowflake,
+    pub session_id: String,
     pub deaf: bool,
     pub mute: bool,
     pub self_deaf: bool,
@@ -24,7 +24,7 @@ impl CachedTypes for VoiceState {}
 
 #[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct VoiceRegion {
-    pub id: Snowflake,
+    pub id: String,
     pub name: String,
     pub vip: bool,
     pub optimal: bool,